@@ -0,0 +1,21 @@
+// include/sudoku.h, the C header for the functions in src/ffi.rs, is kept in the
+// repository so a normal build never writes to the source tree; build with
+// `--features header` to write it again after changing the interface
+#[cfg(feature = "header")]
+fn main() {
+    println!( "cargo:rerun-if-changed=src/ffi.rs" );
+    println!( "cargo:rerun-if-changed=cbindgen.toml" );
+    let crate_dir = std::env::var( "CARGO_MANIFEST_DIR" ).unwrap();
+    let config = cbindgen::Config::from_file( format!( "{}/cbindgen.toml", crate_dir ) ).expect( "cbindgen.toml is not valid" );
+    cbindgen::Builder::new()
+        .with_src( format!( "{}/src/ffi.rs", crate_dir ) )
+        .with_config( config )
+        .generate()
+        .expect( "the C header could not be generated" )
+        .write_to_file( format!( "{}/include/sudoku.h", crate_dir ) );
+}
+
+#[cfg(not(feature = "header"))]
+fn main() {
+    println!( "cargo:rerun-if-changed=build.rs" );
+}
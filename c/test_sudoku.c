@@ -0,0 +1,82 @@
+/* Exercises the C interface of the cdylib, run it with `make -C c test`. */
+
+#include <stdio.h>
+#include <string.h>
+#include "sudoku.h"
+
+static const char *PUZZLE =
+    "..4...3.7.8.........32.1......7......6.5.27.4....1...5.35..6.9..7.3.......9.8.2..";
+static const char *EMPTY =
+    ".................................................................................";
+static const char *CLASH =
+    "11...............................................................................";
+
+static int failures = 0;
+
+static void check( int ok, const char *what ) {
+    printf( "%s %s\n", ok ? "ok  " : "FAIL", what );
+    if ( !ok ) failures++;
+}
+
+/* every row, column and box of a solution holds each digit once */
+static int is_solved( const char *grid ) {
+    for ( int unit = 0; unit < 27; unit++ ) {
+        int seen = 0;
+        for ( int i = 0; i < 9; i++ ) {
+            int row, col;
+            if ( unit < 9 ) { row = unit; col = i; }
+            else if ( unit < 18 ) { row = i; col = unit - 9; }
+            else { row = ( unit - 18 ) / 3 * 3 + i / 3; col = ( unit - 18 ) % 3 * 3 + i % 3; }
+            char c = grid[ row * 9 + col ];
+            if ( c < '1' || c > '9' ) return 0;
+            seen |= 1 << ( c - '1' );
+        }
+        if ( seen != 0x1FF ) return 0;
+    }
+    return 1;
+}
+
+/* the filled cells of a puzzle agree with its solution */
+static int agrees( const char *puzzle, const char *solution ) {
+    for ( int pos = 0; pos < SUDOKU_CELLS; pos++ ) {
+        if ( puzzle[pos] != '.' && puzzle[pos] != solution[pos] ) return 0;
+    }
+    return 1;
+}
+
+int main( void ) {
+    char solution[ SUDOKU_CELLS + 1 ];
+    char puzzle[ SUDOKU_CELLS + 1 ];
+
+    check( sudoku_solve( PUZZLE, solution ) == SUDOKU_OK, "solve returns ok" );
+    check( strlen( solution ) == SUDOKU_CELLS && is_solved( solution ), "solve fills a valid grid" );
+    check( agrees( PUZZLE, solution ), "solve keeps the givens" );
+    check( sudoku_solve( CLASH, solution ) == SUDOKU_ERROR_INVALID, "solve refuses clashing givens" );
+    check( sudoku_solve( "123", solution ) == SUDOKU_ERROR_INVALID, "solve refuses a short puzzle" );
+    check( sudoku_solve( NULL, solution ) == SUDOKU_ERROR_NULL, "solve refuses a NULL puzzle" );
+
+    check( sudoku_count( PUZZLE, 2 ) == 1, "count finds one solution" );
+    check( sudoku_count( EMPTY, 1000 ) == 1000, "count stops at the limit" );
+    check( sudoku_count( PUZZLE, 0 ) == SUDOKU_ERROR_ARGUMENT, "count refuses a limit below one" );
+
+    int grade = sudoku_grade( PUZZLE );
+    check( grade >= SUDOKU_EASY && grade <= SUDOKU_EXPERT, "grade is one of the grades" );
+    check( sudoku_grade( CLASH ) == SUDOKU_ERROR_INVALID, "grade refuses clashing givens" );
+
+    grade = sudoku_generate( SUDOKU_MEDIUM, SUDOKU_SYMMETRY_ROTATIONAL, puzzle, solution );
+    check( grade >= SUDOKU_EASY && grade <= SUDOKU_EXPERT, "generate returns a grade" );
+    check( sudoku_count( puzzle, 2 ) == 1, "generate makes a puzzle with one solution" );
+    check( sudoku_grade( puzzle ) == grade, "generate returns the grade of its puzzle" );
+    check( is_solved( solution ) && agrees( puzzle, solution ), "generate writes the solution" );
+    int symmetric = 1;
+    for ( int pos = 0; pos < SUDOKU_CELLS; pos++ ) {
+        if ( ( puzzle[pos] == '.' ) != ( puzzle[ SUDOKU_CELLS - 1 - pos ] == '.' ) ) symmetric = 0;
+    }
+    check( symmetric, "generate keeps rotational symmetry" );
+    check( sudoku_generate( SUDOKU_ANY_DIFFICULTY, SUDOKU_SYMMETRY_NONE, puzzle, NULL ) >= SUDOKU_EASY, "generate takes a NULL solution" );
+    check( sudoku_generate( 7, SUDOKU_SYMMETRY_NONE, puzzle, NULL ) == SUDOKU_ERROR_ARGUMENT, "generate refuses an unknown difficulty" );
+    check( sudoku_generate( SUDOKU_EASY, 3, puzzle, NULL ) == SUDOKU_ERROR_ARGUMENT, "generate refuses an unknown symmetry" );
+
+    printf( "%d failed\n", failures );
+    return failures == 0 ? 0 : 1;
+}
@@ -0,0 +1,92 @@
+#ifndef SUDOKU_H
+#define SUDOKU_H
+
+/* Generated from src/ffi.rs by `cargo build --features header`, do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// The number of cells in a grid. Solution and puzzle buffers need one more
+// byte than this for the terminating NUL.
+#define SUDOKU_CELLS 81
+
+#define SUDOKU_OK 0
+
+// A pointer argument is NULL.
+#define SUDOKU_ERROR_NULL -1
+
+// The puzzle is not SUDOKU_CELLS cells, or two of its givens clash.
+#define SUDOKU_ERROR_INVALID -2
+
+// The puzzle has no solution.
+#define SUDOKU_ERROR_NO_SOLUTION -3
+
+// A difficulty or symmetry argument is out of range.
+#define SUDOKU_ERROR_ARGUMENT -4
+
+// The solver failed unexpectedly.
+#define SUDOKU_ERROR_INTERNAL -5
+
+#define SUDOKU_EASY 0
+
+#define SUDOKU_MEDIUM 1
+
+#define SUDOKU_HARD 2
+
+#define SUDOKU_EXPERT 3
+
+// Generate a puzzle of whatever difficulty comes up first.
+#define SUDOKU_ANY_DIFFICULTY -1
+
+#define SUDOKU_SYMMETRY_NONE 0
+
+#define SUDOKU_SYMMETRY_ROTATIONAL 1
+
+#define SUDOKU_SYMMETRY_MIRROR 2
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Solves `puzzle` and writes the first solution found to `solution`.
+// Returns SUDOKU_OK, or an error code leaving `solution` untouched.
+//
+// # Safety
+//
+// `puzzle` must be a NUL terminated string and `solution` must point to at
+// least SUDOKU_CELLS + 1 writable bytes.
+int sudoku_solve(const char *puzzle, char *solution);
+
+// Counts the solutions of `puzzle`, stopping at `limit`. A limit of 2 tells
+// a puzzle with one solution from one with several. Returns the count.
+//
+// # Safety
+//
+// `puzzle` must be a NUL terminated string.
+int64_t sudoku_count(const char *puzzle, int64_t limit);
+
+// Generates a puzzle with one solution, writing it to `puzzle` and, when
+// `solution` is not NULL, its solution to `solution`. `difficulty` is one
+// of the SUDOKU_EASY to SUDOKU_EXPERT grades or SUDOKU_ANY_DIFFICULTY, and
+// after a number of tries the last puzzle is kept whatever its grade.
+// `symmetry` is a SUDOKU_SYMMETRY_* value. Returns the grade of the puzzle.
+//
+// # Safety
+//
+// `puzzle`, and `solution` unless NULL, must point to at least
+// SUDOKU_CELLS + 1 writable bytes.
+int sudoku_generate(int difficulty, int symmetry, char *puzzle, char *solution);
+
+// Grades `puzzle` by how much of it is left for trial and error once the
+// hidden singles run out. Returns one of SUDOKU_EASY to SUDOKU_EXPERT.
+//
+// # Safety
+//
+// `puzzle` must be a NUL terminated string.
+int sudoku_grade(const char *puzzle);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* SUDOKU_H */
@@ -0,0 +1,300 @@
+use crate::relations::parse_cell;
+use crate::GRID_SQRT;
+use serde::Deserialize;
+
+// the clues of one puzzle as written in a constraint file, a json array with an
+// entry for every puzzle line:
+//
+// [ { "thermometers": [ ["r1c1", "r1c2", "r1c3"] ],
+//     "arrows": [ { "circle": "r5c5", "cells": ["r5c6", "r6c6"] } ],
+//     "sandwiches": { "rows": [ null, 12 ], "columns": [ 0 ] } } ]
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClueSpec {
+    thermometers: Vec<Vec<String>>,
+    arrows: Vec<ArrowSpec>,
+    sandwiches: SandwichSpec,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ArrowSpec {
+    circle: String,
+    cells: Vec<String>,
+}
+
+// the sum between the 1 and the 9 of each row and column, null where there is no clue
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct SandwichSpec {
+    rows: Vec<Option<usize>>,
+    columns: Vec<Option<usize>>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Clue {
+    // digits strictly increase from the bulb along the path
+    Thermometer( Vec<usize> ),
+    // the circle holds the sum of the digits along the arrow, which may repeat
+    Arrow { circle: usize, cells: Vec<usize> },
+    // the digits between the 1 and the 9 of a row or column add up to `sum`
+    Sandwich { cells: Vec<usize>, sum: usize },
+}
+
+impl Clue {
+    pub fn cells( &self ) -> Vec<usize> {
+        match self {
+            Clue::Thermometer( cells ) => cells.clone(),
+            Clue::Arrow { circle, cells } => std::iter::once( *circle ).chain( cells.iter().copied() ).collect(),
+            Clue::Sandwich { cells, .. } => cells.clone(),
+        }
+    }
+
+    // whether the clue can still be met with `value` at `pos` and the rest of
+    // the grid as it is, empty cells counting as anything
+    pub fn feasible_with( &self, solution: &[usize], pos: usize, value: usize ) -> bool {
+        let value_at = |cell: usize| if cell == pos { value } else { solution[cell] };
+        match self {
+            Clue::Thermometer( cells ) => {
+                // the lowest digit each cell can take given the cells before it
+                let mut lowest = 1;
+                for &cell in cells.iter() {
+                    let v = value_at( cell );
+                    if v == 0 {
+                        lowest += 1;
+                    } else {
+                        if v < lowest { return false; }
+                        lowest = v + 1;
+                    }
+                }
+                lowest <= GRID_SQRT + 1
+            },
+            Clue::Arrow { circle, cells } => {
+                let mut total = 0;
+                let mut empty = 0;
+                for &cell in cells.iter() {
+                    let v = value_at( cell );
+                    if v == 0 { empty += 1; } else { total += v; }
+                }
+                let ( low, high ) = ( total + empty, total + empty * GRID_SQRT );
+                match value_at( *circle ) {
+                    0 => low <= GRID_SQRT,
+                    c => low <= c && c <= high,
+                }
+            },
+            // tries every place the 1 and the 9 could still go
+            Clue::Sandwich { cells, sum } => {
+                let values: Vec<usize> = cells.iter().map( |&cell| value_at( cell ) ).collect();
+                let places = |digit: usize| -> Vec<usize> {
+                    match values.iter().position( |&v| v == digit ) {
+                        Some( i ) => vec![ i ],
+                        None => ( 0..values.len() ).filter( |&i| values[i] == 0 ).collect(),
+                    }
+                };
+                let nines = places( GRID_SQRT );
+                // the empty cells in between take distinct digits the line has not used yet
+                let free: Vec<usize> = ( 2..GRID_SQRT ).filter( |d| !values.contains( d ) ).collect();
+                places( 1 ).into_iter().any( |one| nines.iter().any( |&nine| {
+                    if one == nine { return false; }
+                    let mut total = 0;
+                    let mut empty = 0;
+                    for &v in values[ one.min( nine )+1..one.max( nine ) ].iter() {
+                        if v == 0 { empty += 1; } else { total += v; }
+                    }
+                    if empty > free.len() { return false; }
+                    let low: usize = free[ ..empty ].iter().sum();
+                    let high: usize = free[ free.len()-empty.. ].iter().sum();
+                    total + low <= *sum && *sum <= total + high
+                }))
+            },
+        }
+    }
+
+    // whether the clue holds on a grid with every one of its cells filled
+    pub fn holds( &self, solution: &[usize] ) -> bool {
+        match self {
+            Clue::Thermometer( cells ) => {
+                solution[ cells[0] ] != 0 && cells.windows( 2 ).all( |pair| solution[ pair[0] ] < solution[ pair[1] ] )
+            },
+            Clue::Arrow { circle, cells } => {
+                cells.iter().all( |&cell| solution[cell] != 0 )
+                    && cells.iter().map( |&cell| solution[cell] ).sum::<usize>() == solution[ *circle ]
+            },
+            Clue::Sandwich { cells, sum } => {
+                let place = |digit: usize| cells.iter().position( |&cell| solution[cell] == digit );
+                match ( place( 1 ), place( GRID_SQRT ) ) {
+                    ( Some( one ), Some( nine ) ) => {
+                        cells[ one.min( nine )+1..one.max( nine ) ].iter().map( |&cell| solution[cell] ).sum::<usize>() == *sum
+                    },
+                    _ => false,
+                }
+            },
+        }
+    }
+}
+
+// the clues each of `size` cells takes part in, as indexes into `clues`
+pub fn clue_index( clues: &[Clue], size: usize ) -> Vec<Vec<usize>> {
+    let mut clues_of = vec![ Vec::new(); size ];
+    for (c, clue) in clues.iter().enumerate() {
+        for cell in clue.cells() {
+            if !clues_of[cell].contains( &c ) { clues_of[cell].push( c ); }
+        }
+    }
+    clues_of
+}
+
+pub fn clues_satisfied( clues: &[Clue], solution: &[usize] ) -> bool {
+    clues.iter().all( |clue| clue.holds( solution ) )
+}
+
+pub fn parse_clue_file( json: &str ) -> Result<Vec<ClueSpec>, String> {
+    serde_json::from_str( json ).map_err( |e| format!( "The constraint file is not valid: {}", e ) )
+}
+
+fn parse_cells( cells: &[String] ) -> Result<Vec<usize>, String> {
+    cells.iter()
+        .map( |cell| parse_cell( cell ).ok_or_else( || format!( "'{}' is not a valid cell.", cell ) ) )
+        .collect()
+}
+
+fn sandwich_clues( sums: &[Option<usize>], line: impl Fn( usize, usize ) -> usize, kind: &str ) -> Result<Vec<Clue>, String> {
+    if sums.len() > GRID_SQRT {
+        return Err( format!( "There are more than {} sandwich {}.", GRID_SQRT, kind ) );
+    }
+    let max_sum = ( 2..GRID_SQRT ).sum::<usize>();
+    let mut clues = Vec::new();
+    for (n, sum) in sums.iter().enumerate() {
+        if let Some( sum ) = *sum {
+            if sum > max_sum {
+                return Err( format!( "A sandwich sum cannot be {}.", sum ) );
+            }
+            clues.push( Clue::Sandwich { cells: ( 0..GRID_SQRT ).map( |i| line( n, i ) ).collect(), sum } );
+        }
+    }
+    Ok(clues)
+}
+
+pub fn parse_clues( spec: &ClueSpec ) -> Result<Vec<Clue>, String> {
+    let mut clues = Vec::new();
+    for thermometer in spec.thermometers.iter() {
+        let cells = parse_cells( thermometer )?;
+        if cells.len() < 2 || cells.len() > GRID_SQRT {
+            return Err( format!( "A thermometer must have 2 to {} cells.", GRID_SQRT ) );
+        }
+        clues.push( Clue::Thermometer( cells ) );
+    }
+    for arrow in spec.arrows.iter() {
+        let circle = parse_cells( std::slice::from_ref( &arrow.circle ) )?[0];
+        let cells = parse_cells( &arrow.cells )?;
+        if cells.is_empty() || cells.contains( &circle ) {
+            return Err( format!( "The arrow from {} must have cells outside its circle.", arrow.circle ) );
+        }
+        clues.push( Clue::Arrow { circle, cells } );
+    }
+    clues.extend( sandwich_clues( &spec.sandwiches.rows, |n, i| n * GRID_SQRT + i, "rows" )? );
+    clues.extend( sandwich_clues( &spec.sandwiches.columns, |n, i| i * GRID_SQRT + n, "columns" )? );
+    Ok(clues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{grid, SOLUTION};
+    use crate::GRID_SIZE;
+
+    // the solution's first rows are 647529138, 285413769 and 193867452
+    fn thermometer() -> Clue { Clue::Thermometer( vec![ 9, 0, 2, 17 ] ) }
+    fn arrow() -> Clue { Clue::Arrow { circle: 5, cells: vec![ 3, 4, 9 ] } }
+    fn row_sandwich( row: usize, sum: usize ) -> Clue {
+        Clue::Sandwich { cells: ( 0..GRID_SQRT ).map( |i| row * GRID_SQRT + i ).collect(), sum }
+    }
+
+    #[test]
+    fn clues_hold_on_their_solution() {
+        let solution = grid( SOLUTION );
+        assert!( thermometer().holds( &solution ) );
+        assert!( arrow().holds( &solution ) );
+        assert!( row_sandwich( 0, 0 ).holds( &solution ) );
+        assert!( row_sandwich( 1, 16 ).holds( &solution ) );
+        assert!( Clue::Sandwich { cells: ( 0..GRID_SQRT ).map( |i| i * GRID_SQRT ).collect(), sum: 27 }.holds( &solution ) );
+        assert!( clues_satisfied( &[ thermometer(), arrow(), row_sandwich( 1, 16 ) ], &solution ) );
+    }
+
+    #[test]
+    fn broken_clues_do_not_hold() {
+        let solution = grid( SOLUTION );
+        assert!( !Clue::Thermometer( vec![ 0, 9 ] ).holds( &solution ) );
+        assert!( !Clue::Arrow { circle: 5, cells: vec![ 3, 4 ] }.holds( &solution ) );
+        assert!( !row_sandwich( 1, 15 ).holds( &solution ) );
+        assert!( !clues_satisfied( &[ thermometer(), row_sandwich( 0, 1 ) ], &solution ) );
+        // a clue over cells that are still empty does not hold either
+        assert!( !thermometer().holds( &[0; GRID_SIZE] ) );
+        assert!( !arrow().holds( &[0; GRID_SIZE] ) );
+        assert!( !row_sandwich( 0, 0 ).holds( &[0; GRID_SIZE] ) );
+    }
+
+    #[test]
+    fn thermometers_leave_room_along_the_bulb() {
+        let empty = [0; GRID_SIZE];
+        assert!( thermometer().feasible_with( &empty, 9, 6 ) );
+        assert!( !thermometer().feasible_with( &empty, 9, 7 ) );
+        assert!( !thermometer().feasible_with( &empty, 17, 3 ) );
+        let mut partial = empty;
+        partial[0] = 5;
+        assert!( !thermometer().feasible_with( &partial, 9, 5 ) );
+        assert!( thermometer().feasible_with( &partial, 2, 6 ) );
+        assert!( !thermometer().feasible_with( &partial, 2, 9 ) );
+    }
+
+    #[test]
+    fn arrows_bound_their_circle() {
+        let empty = [0; GRID_SIZE];
+        assert!( !arrow().feasible_with( &empty, 5, 2 ) );
+        assert!( arrow().feasible_with( &empty, 5, 3 ) );
+        let mut partial = empty;
+        partial[3] = 5;
+        partial[4] = 2;
+        assert!( arrow().feasible_with( &partial, 9, 2 ) );
+        assert!( arrow().feasible_with( &partial, 5, 9 ) );
+        assert!( !arrow().feasible_with( &partial, 5, 7 ) );
+        partial[5] = 9;
+        assert!( !arrow().feasible_with( &partial, 9, 3 ) );
+    }
+
+    #[test]
+    fn sandwiches_check_what_fits_between_the_one_and_the_nine() {
+        let empty = [0; GRID_SIZE];
+        assert!( row_sandwich( 0, 0 ).feasible_with( &empty, 0, 1 ) );
+        assert!( row_sandwich( 0, 35 ).feasible_with( &empty, 0, 1 ) );
+        // with the 1 and 9 at the ends the seven cells between hold 2 to 8
+        let mut ends = empty;
+        ends[0] = 1;
+        assert!( !row_sandwich( 0, 0 ).feasible_with( &ends, 8, 9 ) );
+        assert!( row_sandwich( 0, 35 ).feasible_with( &ends, 8, 9 ) );
+        assert!( row_sandwich( 0, 2 ).feasible_with( &ends, 2, 9 ) );
+        assert!( !row_sandwich( 0, 3 ).feasible_with( &ends, 1, 9 ) );
+    }
+
+    #[test]
+    fn clue_files_parse_and_reject_bad_clues() {
+        let specs = parse_clue_file( r#"[ { "thermometers": [ ["r1c1", "r1c2"] ],
+            "arrows": [ { "circle": "r5c5", "cells": ["r5c6"] } ],
+            "sandwiches": { "rows": [ null, 12 ], "columns": [ 0 ] } }, {} ]"# ).unwrap();
+        assert_eq!( specs.len(), 2 );
+        let clues = parse_clues( &specs[0] ).unwrap();
+        assert_eq!( clues.len(), 4 );
+        assert_eq!( clues[0].cells(), vec![ 0, 1 ] );
+        assert_eq!( clues[1].cells(), vec![ 40, 41 ] );
+        assert_eq!( clue_index( &clues, GRID_SIZE )[40], vec![ 1 ] );
+        assert_eq!( clue_index( &clues, GRID_SIZE )[9], vec![ 2, 3 ] );
+        assert!( parse_clues( &specs[1] ).unwrap().is_empty() );
+
+        assert!( parse_clue_file( r#"[ { "thermo": [] } ]"# ).is_err() );
+        for bad in &[ r#"{ "thermometers": [ ["r1c1"] ] }"#, r#"{ "thermometers": [ ["r1c1", "r0c2"] ] }"#,
+                      r#"{ "arrows": [ { "circle": "r5c5", "cells": ["r5c5"] } ] }"#, r#"{ "sandwiches": { "rows": [ 36 ] } }"# ] {
+            let spec: ClueSpec = serde_json::from_str( bad ).unwrap();
+            assert!( parse_clues( &spec ).is_err(), "{}", bad );
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// The C interface of the cdylib. Puzzles and solutions are NUL terminated
+// strings of SUDOKU_CELLS characters in reading order, with `.` or `0` for an
+// empty cell, and only standard puzzles are handled. Every function returns a
+// negative SUDOKU_ERROR_* code when it fails. build.rs writes the `///` comments
+// here into include/sudoku.h along with the declarations, so keep them for C readers.
+
+use crate::{AppOptions, Difficulty, Sudoku, Symmetry, GRID_SIZE, NUM_TO_TEXT};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+
+/// The number of cells in a grid. Solution and puzzle buffers need one more
+/// byte than this for the terminating NUL.
+pub const SUDOKU_CELLS: usize = 81;
+const _: () = assert!( SUDOKU_CELLS == GRID_SIZE );
+
+pub const SUDOKU_OK: c_int = 0;
+/// A pointer argument is NULL.
+pub const SUDOKU_ERROR_NULL: c_int = -1;
+/// The puzzle is not SUDOKU_CELLS cells, or two of its givens clash.
+pub const SUDOKU_ERROR_INVALID: c_int = -2;
+/// The puzzle has no solution.
+pub const SUDOKU_ERROR_NO_SOLUTION: c_int = -3;
+/// A difficulty or symmetry argument is out of range.
+pub const SUDOKU_ERROR_ARGUMENT: c_int = -4;
+/// The solver failed unexpectedly.
+pub const SUDOKU_ERROR_INTERNAL: c_int = -5;
+
+pub const SUDOKU_EASY: c_int = 0;
+pub const SUDOKU_MEDIUM: c_int = 1;
+pub const SUDOKU_HARD: c_int = 2;
+pub const SUDOKU_EXPERT: c_int = 3;
+/// Generate a puzzle of whatever difficulty comes up first.
+pub const SUDOKU_ANY_DIFFICULTY: c_int = -1;
+
+pub const SUDOKU_SYMMETRY_NONE: c_int = 0;
+pub const SUDOKU_SYMMETRY_ROTATIONAL: c_int = 1;
+pub const SUDOKU_SYMMETRY_MIRROR: c_int = 2;
+
+const DIFFICULTIES: [Difficulty; 4] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard, Difficulty::Expert];
+const SYMMETRIES: [Symmetry; 3] = [Symmetry::None, Symmetry::Rotational, Symmetry::Mirror];
+
+// a panic must not unwind into the caller's C frames
+fn guard( f: impl FnOnce() -> Result<i64, c_int> ) -> i64 {
+    match panic::catch_unwind( AssertUnwindSafe( f ) ) {
+        Ok( Ok( value ) ) => value,
+        Ok( Err( code ) ) => i64::from( code ),
+        Err( _ ) => i64::from( SUDOKU_ERROR_INTERNAL ),
+    }
+}
+
+fn new_sudoku() -> Sudoku {
+    Sudoku::new( AppOptions::default() )
+}
+
+// loads a puzzle string, refusing clashing givens since the solver trusts them
+unsafe fn load( sudoku: &mut Sudoku, puzzle: *const c_char ) -> Result<(), c_int> {
+    if puzzle.is_null() { return Err( SUDOKU_ERROR_NULL ); }
+    let line = CStr::from_ptr( puzzle ).to_str().map_err( |_| SUDOKU_ERROR_INVALID )?;
+    if line.len() != GRID_SIZE { return Err( SUDOKU_ERROR_INVALID ); }
+    sudoku.initialize_with_line( line, None ).map_err( |_| SUDOKU_ERROR_INVALID )?;
+    sudoku.clash().map_err( |_| SUDOKU_ERROR_INVALID )
+}
+
+unsafe fn write_grid( grid: &[usize], out: *mut c_char ) {
+    for (pos, &value) in grid.iter().enumerate() {
+        *out.add( pos ) = NUM_TO_TEXT[ value ] as c_char;
+    }
+    *out.add( grid.len() ) = 0;
+}
+
+/// Solves `puzzle` and writes the first solution found to `solution`.
+/// Returns SUDOKU_OK, or an error code leaving `solution` untouched.
+///
+/// # Safety
+///
+/// `puzzle` must be a NUL terminated string and `solution` must point to at
+/// least SUDOKU_CELLS + 1 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_solve( puzzle: *const c_char, solution: *mut c_char ) -> c_int {
+    guard( || {
+        if solution.is_null() { return Err( SUDOKU_ERROR_NULL ); }
+        let mut sudoku = new_sudoku();
+        load( &mut sudoku, puzzle )?;
+        sudoku.solve_fast( 1 );
+        if sudoku.solution_count != 1 { return Err( SUDOKU_ERROR_NO_SOLUTION ); }
+        write_grid( &sudoku.solution, solution );
+        Ok( i64::from( SUDOKU_OK ) )
+    }) as c_int
+}
+
+/// Counts the solutions of `puzzle`, stopping at `limit`. A limit of 2 tells
+/// a puzzle with one solution from one with several. Returns the count.
+///
+/// # Safety
+///
+/// `puzzle` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_count( puzzle: *const c_char, limit: i64 ) -> i64 {
+    guard( || {
+        if limit < 1 { return Err( SUDOKU_ERROR_ARGUMENT ); }
+        let mut sudoku = new_sudoku();
+        load( &mut sudoku, puzzle )?;
+        sudoku.solve_fast( limit as usize );
+        Ok( sudoku.solution_count as i64 )
+    })
+}
+
+/// Generates a puzzle with one solution, writing it to `puzzle` and, when
+/// `solution` is not NULL, its solution to `solution`. `difficulty` is one
+/// of the SUDOKU_EASY to SUDOKU_EXPERT grades or SUDOKU_ANY_DIFFICULTY, and
+/// after a number of tries the last puzzle is kept whatever its grade.
+/// `symmetry` is a SUDOKU_SYMMETRY_* value. Returns the grade of the puzzle.
+///
+/// # Safety
+///
+/// `puzzle`, and `solution` unless NULL, must point to at least
+/// SUDOKU_CELLS + 1 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_generate( difficulty: c_int, symmetry: c_int, puzzle: *mut c_char, solution: *mut c_char ) -> c_int {
+    guard( || {
+        if puzzle.is_null() { return Err( SUDOKU_ERROR_NULL ); }
+        let wanted = match difficulty {
+            SUDOKU_ANY_DIFFICULTY => None,
+            _ => Some( *DIFFICULTIES.get( difficulty as usize ).ok_or( SUDOKU_ERROR_ARGUMENT )? ),
+        };
+        let symmetry = *SYMMETRIES.get( symmetry as usize ).ok_or( SUDOKU_ERROR_ARGUMENT )?;
+        let mut sudoku = Sudoku::new( AppOptions { difficulty: wanted, symmetry, ..AppOptions::default() } );
+        sudoku.generate();
+        let grade = sudoku.grade();
+        write_grid( &sudoku.puzzle, puzzle );
+        if !solution.is_null() {
+            sudoku.solve_fast( 1 );
+            write_grid( &sudoku.solution, solution );
+        }
+        Ok( DIFFICULTIES.iter().position( |&d| d == grade ).unwrap_or( 0 ) as i64 )
+    }) as c_int
+}
+
+/// Grades `puzzle` by how much of it is left for trial and error once the
+/// hidden singles run out. Returns one of SUDOKU_EASY to SUDOKU_EXPERT.
+///
+/// # Safety
+///
+/// `puzzle` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_grade( puzzle: *const c_char ) -> c_int {
+    guard( || {
+        let mut sudoku = new_sudoku();
+        load( &mut sudoku, puzzle )?;
+        sudoku.solve_fast( 1 );
+        if sudoku.solution_count != 1 { return Err( SUDOKU_ERROR_NO_SOLUTION ); }
+        let grade = sudoku.grade();
+        Ok( DIFFICULTIES.iter().position( |&d| d == grade ).unwrap_or( 0 ) as i64 )
+    }) as c_int
+}
@@ -0,0 +1,311 @@
+use crate::units::{self, Board};
+use crate::{Variant, GRID_SQRT, NUM_TO_BITMAP};
+use std::fmt;
+
+// the techniques a hint can use, easiest first
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Technique {
+    HiddenSingle,
+    NakedSingle,
+    LockedCandidates,
+    NakedPair,
+    HiddenPair,
+}
+
+impl fmt::Display for Technique {
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+        match self {
+            Technique::HiddenSingle => write!( f, "hidden single" ),
+            Technique::NakedSingle => write!( f, "naked single" ),
+            Technique::LockedCandidates => write!( f, "locked candidates" ),
+            Technique::NakedPair => write!( f, "naked pair" ),
+            Technique::HiddenPair => write!( f, "hidden pair" ),
+        }
+    }
+}
+
+// One deduction: a single places `digits[0]` at `cells[0]`, the other
+// techniques remove candidates as (cell, digit). `units` are the units the
+// deduction is made in, as indexes into the grid's units. The variant names
+// the cells and units of its board.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hint {
+    pub technique: Technique,
+    pub cells: Vec<usize>,
+    pub digits: Vec<usize>,
+    pub units: Vec<usize>,
+    pub eliminations: Vec<(usize, usize)>,
+    variant: Variant,
+}
+
+impl Hint {
+    // the cell and digit to fill in, for the singles
+    pub fn placement( &self ) -> Option<(usize, usize)> {
+        match self.technique {
+            Technique::HiddenSingle | Technique::NakedSingle => Some( ( self.cells[0], self.digits[0] ) ),
+            _ => None,
+        }
+    }
+
+    pub fn cell_name( &self, pos: usize ) -> String {
+        Board::of( self.variant ).cell_name( pos )
+    }
+
+    pub fn unit_name( &self, unit: usize ) -> String {
+        units::unit_name( self.variant, unit )
+    }
+}
+
+fn join<T>( items: &[T], f: impl Fn( &T ) -> String ) -> String {
+    items.iter().map( f ).collect::<Vec<String>>().join( " and " )
+}
+
+impl fmt::Display for Hint {
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+        let cells = join( &self.cells, |&pos| self.cell_name( pos ) );
+        let digits = join( &self.digits, |d| d.to_string() );
+        let unit = self.units.first().map( |&u| self.unit_name( u ) ).unwrap_or_default();
+        match self.technique {
+            Technique::HiddenSingle => write!( f, "{}: {} is the only place for {} in {}", self.technique, cells, digits, unit )?,
+            Technique::NakedSingle => write!( f, "{}: {} is the only candidate left for {}", self.technique, digits, cells )?,
+            Technique::LockedCandidates => write!( f, "{}: {} in {} is confined to {}", self.technique, digits, unit, self.unit_name( self.units[1] ) )?,
+            Technique::NakedPair => write!( f, "{}: {} can only hold {} in {}", self.technique, cells, digits, unit )?,
+            Technique::HiddenPair => write!( f, "{}: {} only go in {} in {}", self.technique, digits, cells, unit )?,
+        }
+        if !self.eliminations.is_empty() {
+            let removed: Vec<String> = self.eliminations.iter().map( |&(pos, d)| format!( "{} from {}", d, self.cell_name( pos ) ) ).collect();
+            write!( f, ", remove {}", removed.join( ", " ) )?;
+        }
+        Ok(())
+    }
+}
+
+fn digits( bits: usize ) -> Vec<usize> {
+    ( 1..GRID_SQRT+1 ).filter( |&d| bits & NUM_TO_BITMAP[d] != 0 ).collect()
+}
+
+#[allow(clippy::needless_range_loop)]
+fn hidden_single( variant: Variant, values: &[usize], candidates: &[usize], units: &[Vec<usize>] ) -> Result<Option<Hint>, String> {
+    for (u, unit) in units.iter().enumerate() {
+        for d in 1..GRID_SQRT+1 {
+            if unit.iter().any( |&pos| values[pos] == d ) { continue; }
+            let places: Vec<usize> = unit.iter().copied().filter( |&pos| candidates[pos] & NUM_TO_BITMAP[d] != 0 ).collect();
+            match places.len() {
+                0 => return Err( format!( "There is no place left for {} in {}.", d, units::unit_name( variant, u ) ) ),
+                1 => return Ok( Some( Hint { technique: Technique::HiddenSingle, cells: places, digits: vec![ d ], units: vec![ u ], eliminations: Vec::new(), variant } ) ),
+                _ => {},
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn naked_single( variant: Variant, values: &[usize], candidates: &[usize] ) -> Result<Option<Hint>, String> {
+    for pos in 0..values.len() {
+        if values[pos] != 0 { continue; }
+        match candidates[pos].count_ones() {
+            0 => return Err( format!( "There is no candidate left for {}.", Board::of( variant ).cell_name( pos ) ) ),
+            1 => return Ok( Some( Hint { technique: Technique::NakedSingle, cells: vec![ pos ], digits: digits( candidates[pos] ), units: Vec::new(), eliminations: Vec::new(), variant } ) ),
+            _ => {},
+        }
+    }
+    Ok(None)
+}
+
+// a digit whose places in one unit all lie in another unit cannot go in the
+// rest of that other unit
+#[allow(clippy::needless_range_loop)]
+fn locked_candidates( variant: Variant, candidates: &[usize], units: &[Vec<usize>] ) -> Option<Hint> {
+    for d in 1..GRID_SQRT+1 {
+        let bitmap = NUM_TO_BITMAP[d];
+        for (u, unit) in units.iter().enumerate() {
+            let places: Vec<usize> = unit.iter().copied().filter( |&pos| candidates[pos] & bitmap != 0 ).collect();
+            if places.len() < 2 { continue; }
+            for (v, other) in units.iter().enumerate() {
+                if v == u || !places.iter().all( |pos| other.contains( pos ) ) { continue; }
+                let eliminations: Vec<(usize, usize)> = other.iter()
+                    .filter( |&pos| !unit.contains( pos ) && candidates[*pos] & bitmap != 0 )
+                    .map( |&pos| ( pos, d ) )
+                    .collect();
+                if !eliminations.is_empty() {
+                    return Some( Hint { technique: Technique::LockedCandidates, cells: places, digits: vec![ d ], units: vec![ u, v ], eliminations, variant } );
+                }
+            }
+        }
+    }
+    None
+}
+
+fn naked_pair( variant: Variant, values: &[usize], candidates: &[usize], units: &[Vec<usize>] ) -> Option<Hint> {
+    for (u, unit) in units.iter().enumerate() {
+        for i in 0..unit.len() {
+            let ( a, bits ) = ( unit[i], candidates[ unit[i] ] );
+            if values[a] != 0 || bits.count_ones() != 2 { continue; }
+            for &b in unit[ i+1.. ].iter() {
+                if candidates[b] != bits { continue; }
+                let mut eliminations = Vec::new();
+                for &pos in unit.iter().filter( |&&pos| pos != a && pos != b ) {
+                    for d in digits( candidates[pos] & bits ) { eliminations.push( ( pos, d ) ); }
+                }
+                if !eliminations.is_empty() {
+                    return Some( Hint { technique: Technique::NakedPair, cells: vec![ a, b ], digits: digits( bits ), units: vec![ u ], eliminations, variant } );
+                }
+            }
+        }
+    }
+    None
+}
+
+fn hidden_pair( variant: Variant, candidates: &[usize], units: &[Vec<usize>] ) -> Option<Hint> {
+    for (u, unit) in units.iter().enumerate() {
+        let places: Vec<Vec<usize>> = ( 0..GRID_SQRT+1 )
+            .map( |d| unit.iter().copied().filter( |&pos| d > 0 && candidates[pos] & NUM_TO_BITMAP[d] != 0 ).collect() )
+            .collect();
+        for d1 in 1..GRID_SQRT+1 {
+            if places[d1].len() != 2 { continue; }
+            for d2 in d1+1..GRID_SQRT+1 {
+                if places[d2] != places[d1] { continue; }
+                let pair = NUM_TO_BITMAP[d1] | NUM_TO_BITMAP[d2];
+                let mut eliminations = Vec::new();
+                for &pos in places[d1].iter() {
+                    for d in digits( candidates[pos] & !pair ) { eliminations.push( ( pos, d ) ); }
+                }
+                if !eliminations.is_empty() {
+                    return Some( Hint { technique: Technique::HiddenPair, cells: places[d1].clone(), digits: vec![ d1, d2 ], units: vec![ u ], eliminations, variant } );
+                }
+            }
+        }
+    }
+    None
+}
+
+// the easiest deduction in a partly filled grid of `variant`: `values` are the
+// filled cells, `candidates` the digits still possible in each empty cell as
+// bitmaps. Errors when the grid has a cell or a digit with nowhere left to go.
+// None means no technique here applies and the grid needs trial and error from
+// this point.
+pub(crate) fn next_hint( variant: Variant, values: &[usize], candidates: &[usize], units: &[Vec<usize>] ) -> Result<Option<Hint>, String> {
+    if let Some( hint ) = hidden_single( variant, values, candidates, units )? { return Ok( Some( hint ) ); }
+    if let Some( hint ) = naked_single( variant, values, candidates )? { return Ok( Some( hint ) ); }
+    Ok( locked_candidates( variant, candidates, units )
+        .or_else( || naked_pair( variant, values, candidates, units ) )
+        .or_else( || hidden_pair( variant, candidates, units ) ) )
+}
+
+// the deductions up to the next digit to place: a hint that only removes
+// candidates is applied to a copy of `candidates` before looking again, so asking
+// twice never gives the same elimination. The last hint places a digit unless
+// the techniques run out first; none at all means the grid needs trial and error.
+pub(crate) fn next_hints( variant: Variant, values: &[usize], candidates: &[usize], units: &[Vec<usize>] ) -> Result<Vec<Hint>, String> {
+    let mut candidates = candidates.to_vec();
+    let mut hints = Vec::new();
+    while let Some( hint ) = next_hint( variant, values, &candidates, units )? {
+        let placed = hint.placement().is_some();
+        for &(pos, d) in hint.eliminations.iter() { candidates[pos] &= !NUM_TO_BITMAP[d]; }
+        hints.push( hint );
+        if placed { break; }
+    }
+    Ok(hints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jigsaw::standard_regions;
+    use crate::units::Constraints;
+    use crate::{ALL_DIGITS, GRID_SIZE};
+
+    // an empty grid where every digit is still possible everywhere
+    fn open_grid() -> ( Vec<usize>, Vec<usize>, Vec<Vec<usize>> ) {
+        let units = Constraints::new( Variant::Standard, &standard_regions(), &[] ).units;
+        ( vec![ 0; GRID_SIZE ], vec![ ALL_DIGITS; GRID_SIZE ], units )
+    }
+
+    fn remove( candidates: &mut [usize], cells: impl Iterator<Item = usize>, digits: &[usize] ) {
+        for pos in cells {
+            for &d in digits.iter() { candidates[pos] &= !NUM_TO_BITMAP[d]; }
+        }
+    }
+
+    fn hint( values: &[usize], candidates: &[usize], units: &[Vec<usize>] ) -> Hint {
+        next_hint( Variant::Standard, values, candidates, units ).unwrap().unwrap()
+    }
+
+    #[test]
+    fn a_digit_with_one_place_in_a_unit_is_a_hidden_single() {
+        let ( values, mut candidates, units ) = open_grid();
+        remove( &mut candidates, 1..9, &[5] );
+        let hint = hint( &values, &candidates, &units );
+        assert_eq!( hint.technique, Technique::HiddenSingle );
+        assert_eq!( hint.placement(), Some( ( 0, 5 ) ) );
+        assert_eq!( hint.to_string(), "hidden single: r1c1 is the only place for 5 in row 1" );
+
+        remove( &mut candidates, 0..1, &[5] );
+        assert_eq!( next_hint( Variant::Standard, &values, &candidates, &units ), Err( "There is no place left for 5 in row 1.".to_string() ) );
+    }
+
+    #[test]
+    fn a_cell_with_one_candidate_is_a_naked_single() {
+        let ( values, mut candidates, units ) = open_grid();
+        candidates[40] = NUM_TO_BITMAP[7];
+        let hint = hint( &values, &candidates, &units );
+        assert_eq!( hint.technique, Technique::NakedSingle );
+        assert_eq!( hint.placement(), Some( ( 40, 7 ) ) );
+        assert_eq!( hint.to_string(), "naked single: 7 is the only candidate left for r5c5" );
+
+        candidates[40] = 0;
+        assert_eq!( next_hint( Variant::Standard, &values, &candidates, &units ), Err( "There is no candidate left for r5c5.".to_string() ) );
+    }
+
+    #[test]
+    fn a_digit_confined_to_a_line_of_a_box_is_locked() {
+        let ( values, mut candidates, units ) = open_grid();
+        remove( &mut candidates, [ 9, 10, 11, 18, 19, 20 ].iter().copied(), &[3] );
+        let hint = hint( &values, &candidates, &units );
+        assert_eq!( hint.technique, Technique::LockedCandidates );
+        assert_eq!( hint.cells, vec![ 0, 1, 2 ] );
+        assert_eq!( hint.units, vec![ 2 * GRID_SQRT, 0 ] );
+        assert_eq!( hint.eliminations, ( 3..9 ).map( |pos| ( pos, 3 ) ).collect::<Vec<(usize, usize)>>() );
+        assert!( hint.to_string().starts_with( "locked candidates: 3 in box 1 is confined to row 1, remove 3 from r1c4" ) );
+        assert_eq!( hint.placement(), None );
+    }
+
+    #[test]
+    fn two_cells_with_the_same_two_candidates_are_a_naked_pair() {
+        let ( values, mut candidates, units ) = open_grid();
+        candidates[0] = NUM_TO_BITMAP[1] | NUM_TO_BITMAP[2];
+        candidates[1] = candidates[0];
+        let hint = hint( &values, &candidates, &units );
+        assert_eq!( hint.technique, Technique::NakedPair );
+        assert_eq!( ( hint.cells.clone(), hint.digits.clone(), hint.units.clone() ), ( vec![ 0, 1 ], vec![ 1, 2 ], vec![ 0 ] ) );
+        assert_eq!( hint.eliminations.len(), 2 * 7 );
+        assert!( hint.eliminations.iter().all( |&(pos, _)| ( 2..9 ).contains( &pos ) ) );
+    }
+
+    #[test]
+    fn two_digits_with_the_same_two_places_are_a_hidden_pair() {
+        let ( values, mut candidates, units ) = open_grid();
+        remove( &mut candidates, ( 1..9 ).filter( |&pos| pos != 4 ), &[4, 6] );
+        let hint = hint( &values, &candidates, &units );
+        assert_eq!( hint.technique, Technique::HiddenPair );
+        assert_eq!( ( hint.cells.clone(), hint.digits.clone() ), ( vec![ 0, 4 ], vec![ 4, 6 ] ) );
+        assert_eq!( hint.eliminations.len(), 2 * 7 );
+        assert_eq!( hint.to_string().split( ',' ).next(), Some( "hidden pair: 4 and 6 only go in r1c1 and r1c5 in row 1" ) );
+    }
+
+    #[test]
+    fn eliminations_are_applied_until_a_digit_can_be_placed() {
+        let ( values, mut candidates, units ) = open_grid();
+        candidates[0] = NUM_TO_BITMAP[1] | NUM_TO_BITMAP[2];
+        candidates[1] = candidates[0];
+        candidates[2] = candidates[0] | NUM_TO_BITMAP[3];
+        let hints = next_hints( Variant::Standard, &values, &candidates, &units ).unwrap();
+        assert_eq!( hints[0], hint( &values, &candidates, &units ) );
+        assert_eq!( hints.last().and_then( Hint::placement ), Some( ( 2, 3 ) ) );
+        assert!( hints[ ..hints.len()-1 ].iter().all( |hint| hint.placement().is_none() && !hint.eliminations.is_empty() ) );
+        for i in 1..hints.len() { assert!( !hints[ ..i ].contains( &hints[i] ) ); }
+
+        let ( values, candidates, units ) = open_grid();
+        assert!( next_hints( Variant::Standard, &values, &candidates, &units ).unwrap().is_empty() );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{neighbours, GRID_BLCK, GRID_SQRT, GRID_SIZE};
+use rand::Rng;
+
+const REGION_LABELS: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const RANDOM_SWAPS: usize = 300;
+
+// the region of every cell of a standard grid: its block
+pub fn standard_regions() -> [usize; GRID_SIZE] {
+    let mut regions = [0; GRID_SIZE];
+    for (pos, region) in regions.iter_mut().enumerate() {
+        let y = pos / GRID_SQRT;
+        let x = pos % GRID_SQRT;
+        *region = ( y / GRID_BLCK ) * GRID_BLCK + x / GRID_BLCK;
+    }
+    regions
+}
+
+fn is_connected( regions: &[usize; GRID_SIZE], region: usize ) -> bool {
+    let start = match ( 0..GRID_SIZE ).find( |&pos| regions[pos] == region ) {
+        Some( pos ) => pos,
+        None => return false,
+    };
+    let mut seen = [false; GRID_SIZE];
+    let mut stack = vec![ start ];
+    let mut count = 0;
+    seen[start] = true;
+    while let Some( pos ) = stack.pop() {
+        count += 1;
+        for n in neighbours( pos ) {
+            if !seen[n] && regions[n] == region {
+                seen[n] = true;
+                stack.push( n );
+            }
+        }
+    }
+    count == GRID_SQRT
+}
+
+// reads a region map of one letter per cell, e.g. "aaabbbccc..."; every
+// region must be a connected group of GRID_SQRT cells
+pub fn parse_regions( map: &str ) -> Result<[usize; GRID_SIZE], String> {
+    let map = map.as_bytes();
+    if map.len() != GRID_SIZE {
+        return Err( format!( "The region map must have {} cells.", GRID_SIZE ) );
+    }
+    let mut labels: Vec<u8> = Vec::new();
+    let mut regions = [0; GRID_SIZE];
+    for (pos, label) in map.iter().enumerate() {
+        regions[pos] = match labels.iter().position( |l| l == label ) {
+            Some( r ) => r,
+            None => {
+                labels.push( *label );
+                labels.len() - 1
+            }
+        };
+    }
+    if labels.len() != GRID_SQRT {
+        return Err( format!( "The region map must have {} regions.", GRID_SQRT ) );
+    }
+    for (r, label) in labels.iter().enumerate() {
+        if !is_connected( &regions, r ) {
+            return Err( format!( "Region '{}' must be {} connected cells.", *label as char, GRID_SQRT ) );
+        }
+    }
+    Ok(regions)
+}
+
+pub fn format_regions( regions: &[usize; GRID_SIZE] ) -> String {
+    regions.iter().map( |&r| REGION_LABELS[r] as char ).collect()
+}
+
+// distorts the standard blocks by swapping cells across region borders,
+// keeping every region connected
+pub fn random_regions() -> [usize; GRID_SIZE] {
+    let mut rng = rand::thread_rng();
+    let mut regions = standard_regions();
+    let mut swaps = 0;
+    while swaps < RANDOM_SWAPS {
+        let a = rng.gen_range( 0, GRID_SIZE );
+        let others: Vec<usize> = ( 0..GRID_SIZE ).filter( |&pos| regions[pos] != regions[a]
+            && neighbours( pos ).iter().any( |&n| regions[n] == regions[a] ) ).collect();
+        let near: Vec<usize> = neighbours( a ).into_iter().filter( |&n| regions[n] != regions[a] ).collect();
+        if others.is_empty() || near.is_empty() { continue; }
+
+        // a moves into a neighbouring region and a cell of that region bordering a's region moves out
+        let b = near[ rng.gen_range( 0, near.len() ) ];
+        let ( ra, rb ) = ( regions[a], regions[b] );
+        let swap: Vec<usize> = others.into_iter().filter( |&pos| regions[pos] == rb ).collect();
+        if swap.is_empty() { continue; }
+        let c = swap[ rng.gen_range( 0, swap.len() ) ];
+        regions[a] = rb;
+        regions[c] = ra;
+        if is_connected( &regions, ra ) && is_connected( &regions, rb ) {
+            swaps += 1;
+        } else {
+            regions[a] = ra;
+            regions[c] = rb;
+        }
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the blocks of a standard grid with r1c3 and r2c4 traded between the first two
+    const TRADED: &str = "aabbbbcccaaaabbcccaaabbbcccdddeeefffdddeeefffdddeeefffggghhhiiiggghhhiiiggghhhiii";
+
+    #[test]
+    fn a_region_map_reads_back_as_it_was_written() {
+        let regions = parse_regions( TRADED ).unwrap();
+        assert_eq!( regions[2], 1 );
+        assert_eq!( regions[ GRID_SQRT + 3 ], 0 );
+        assert_eq!( format_regions( &regions ), TRADED );
+        assert_eq!( format_regions( &standard_regions() ).len(), GRID_SIZE );
+        assert_eq!( parse_regions( &format_regions( &standard_regions() ) ).unwrap(), standard_regions() );
+    }
+
+    #[test]
+    fn a_region_of_the_wrong_size_is_rejected() {
+        // r2c4 goes back to the second block without r1c3 leaving it
+        let mut map = TRADED.to_string();
+        map.replace_range( GRID_SQRT + 3..GRID_SQRT + 4, "b" );
+        assert_eq!( parse_regions( &map ).unwrap_err(), "Region 'a' must be 9 connected cells." );
+        assert_eq!( parse_regions( &TRADED[1..] ).unwrap_err(), "The region map must have 81 cells." );
+    }
+
+    #[test]
+    fn a_disconnected_region_is_rejected() {
+        // r1c1 and r5c5 trade places, leaving one cell of each block on its own
+        let mut map = TRADED.to_string();
+        map.replace_range( 0..1, "e" );
+        map.replace_range( 4 * GRID_SQRT + 4..4 * GRID_SQRT + 5, "a" );
+        assert_eq!( parse_regions( &map ).unwrap_err(), "Region 'e' must be 9 connected cells." );
+    }
+
+    #[test]
+    fn random_regions_are_connected_and_of_nine_cells() {
+        for _ in 0..5 {
+            let regions = random_regions();
+            for region in 0..GRID_SQRT {
+                assert!( is_connected( &regions, region ) );
+            }
+            assert_eq!( parse_regions( &format_regions( &regions ) ).map( |_| () ), Ok(()) );
+        }
+    }
+}
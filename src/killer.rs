@@ -0,0 +1,254 @@
+use crate::{neighbours, GRID_SQRT, GRID_SIZE, NUM_TO_BITMAP};
+use rand::Rng;
+use std::sync::OnceLock;
+
+pub const NO_CAGE: usize = usize::MAX;
+const MAX_CAGE_SIZE: usize = 5;
+const CAGE_LABELS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+const MAX_SUM: usize = GRID_SQRT * ( GRID_SQRT + 1 ) / 2;
+
+// the sum of the digits in every bitmap of digits
+const MASK_SUMS: [usize; 1 << GRID_SQRT] = mask_sums();
+
+const fn mask_sums() -> [usize; 1 << GRID_SQRT] {
+    let mut sums = [0; 1 << GRID_SQRT];
+    let mut mask = 0;
+    while mask < sums.len() {
+        let mut bit = 0;
+        while bit < GRID_SQRT {
+            if mask & ( 1 << bit ) != 0 { sums[mask] += bit + 1; }
+            bit += 1;
+        }
+        mask += 1;
+    }
+    sums
+}
+
+// a group of cells whose digits add up to `sum` without repeating
+#[derive(Clone, Debug)]
+pub struct Cage {
+    pub sum: usize,
+    pub cells: Vec<usize>,
+}
+
+// every bitmap of `size` distinct digits adding up to `sum`, indexed by size then sum
+fn combinations( size: usize, sum: usize ) -> &'static [usize] {
+    static COMBINATIONS: OnceLock<Vec<Vec<Vec<usize>>>> = OnceLock::new();
+    let table = COMBINATIONS.get_or_init( || {
+        let mut table = vec![ vec![ Vec::new(); MAX_SUM+1 ]; GRID_SQRT+1 ];
+        for mask in 0..MASK_SUMS.len() {
+            table[ mask.count_ones() as usize ][ MASK_SUMS[mask] ].push( mask );
+        }
+        table
+    });
+    if size > GRID_SQRT || sum > MAX_SUM { return &[]; }
+    &table[size][sum]
+}
+
+// the digits that can still appear in a cage with `empty` unsolved cells adding up to
+// `remaining`, given the digits already `used` in it
+pub fn allowed_bits( empty: usize, remaining: usize, used: usize ) -> usize {
+    let mut allowed = 0;
+    for &mask in combinations( empty, remaining ) {
+        if mask & used == 0 { allowed |= mask; }
+    }
+    allowed
+}
+
+// the cage of each of `size` cells, NO_CAGE outside every cage
+pub fn cage_index( cages: &[Cage], size: usize ) -> Vec<usize> {
+    let mut cage_of = vec![ NO_CAGE; size ];
+    for (c, cage) in cages.iter().enumerate() {
+        for &cell in cage.cells.iter() { cage_of[cell] = c; }
+    }
+    cage_of
+}
+
+// reads a cage map of one label per cell ('.' for cells outside any cage)
+// followed by a `label=sum` field for every label, e.g. "aab.. a=3 b=7"
+pub fn parse_cages( map: &str, sums: &[&str] ) -> Result<Vec<Cage>, String> {
+    let map = map.as_bytes();
+    if map.len() != GRID_SIZE {
+        return Err( format!( "The cage map must have {} cells.", GRID_SIZE ) );
+    }
+    let mut labels: Vec<u8> = Vec::new();
+    let mut cages: Vec<Cage> = Vec::new();
+    for field in sums {
+        let mut parts = field.splitn( 2, '=' );
+        let label = parts.next().unwrap_or( "" ).as_bytes();
+        let sum = parts.next().and_then( |s| s.parse::<usize>().ok() );
+        match ( label, sum ) {
+            ( [label], Some( sum ) ) if !labels.contains( label ) => {
+                labels.push( *label );
+                cages.push( Cage { sum, cells: Vec::new() } );
+            }
+            _ => return Err( format!( "'{}' is not a valid cage sum.", field ) ),
+        }
+    }
+    for (pos, label) in map.iter().enumerate() {
+        if *label == b'.' { continue; }
+        match labels.iter().position( |l| l == label ) {
+            Some( c ) => cages[c].cells.push( pos ),
+            None => return Err( format!( "Cage '{}' has no sum.", *label as char ) ),
+        }
+    }
+    for (label, cage) in labels.iter().zip( cages.iter() ) {
+        if cage.cells.is_empty() || cage.cells.len() > GRID_SQRT || allowed_bits( cage.cells.len(), cage.sum, 0 ) == 0 {
+            return Err( format!( "Cage '{}' cannot add up to {}.", *label as char, cage.sum ) );
+        }
+    }
+    Ok(cages)
+}
+
+pub fn format_cages( cages: &[Cage] ) -> String {
+    let mut map = vec![ b'.'; GRID_SIZE ];
+    let mut sums = String::new();
+    for (cage, &label) in cages.iter().zip( CAGE_LABELS.iter() ) {
+        for &cell in cage.cells.iter() { map[cell] = label; }
+        sums += &format!( " {}={}", label as char, cage.sum );
+    }
+    String::from_utf8( map ).unwrap() + &sums
+}
+
+pub fn cages_satisfied( cages: &[Cage], solution: &[usize] ) -> bool {
+    cages.iter().all( |cage| {
+        let mut used = 0;
+        let mut total = 0;
+        for &cell in cage.cells.iter() {
+            let bit = NUM_TO_BITMAP[ solution[cell] ];
+            if bit == 0 || used & bit != 0 { return false; }
+            used |= bit;
+            total += solution[cell];
+        }
+        total == cage.sum
+    })
+}
+
+// grows cages of orthogonally connected cells over a solved grid, never
+// repeating a digit inside a cage; single cells are merged into a neighbour.
+// Fails when there are more cages than labels to write them out with.
+pub fn random_cages( solution: &[usize] ) -> Result<Vec<Cage>, String> {
+    let mut rng = rand::thread_rng();
+    let mut cage_of = [NO_CAGE; GRID_SIZE];
+    let mut cages: Vec<Cage> = Vec::new();
+
+    let mut order: Vec<usize> = ( 0..GRID_SIZE ).collect();
+    for n in 0..order.len() {
+        let i = rng.gen_range( 0, order.len() - n );
+        let last = order.len() - n - 1;
+        order.swap( i, last );
+    }
+
+    for &start in order.iter() {
+        if cage_of[start] != NO_CAGE { continue; }
+        let target = rng.gen_range( 2, MAX_CAGE_SIZE+1 );
+        let c = cages.len();
+        let mut cage = Cage { sum: solution[start], cells: vec![ start ] };
+        let mut used = NUM_TO_BITMAP[ solution[start] ];
+        cage_of[start] = c;
+        while cage.cells.len() < target {
+            let candidates: Vec<usize> = cage.cells.iter()
+                .flat_map( |&cell| neighbours( cell ) )
+                .filter( |&cell| cage_of[cell] == NO_CAGE && used & NUM_TO_BITMAP[ solution[cell] ] == 0 )
+                .collect();
+            if candidates.is_empty() { break; }
+            let cell = candidates[ rng.gen_range( 0, candidates.len() ) ];
+            cage_of[cell] = c;
+            used |= NUM_TO_BITMAP[ solution[cell] ];
+            cage.sum += solution[cell];
+            cage.cells.push( cell );
+        }
+        cages.push( cage );
+    }
+
+    for c in 0..cages.len() {
+        if cages[c].cells.len() != 1 { continue; }
+        let cell = cages[c].cells[0];
+        let bit = NUM_TO_BITMAP[ solution[cell] ];
+        let target = neighbours( cell ).into_iter().map( |n| cage_of[n] ).find( |&t| {
+            t != c && cages[t].cells.len() < MAX_CAGE_SIZE
+                && cages[t].cells.iter().all( |&other| NUM_TO_BITMAP[ solution[other] ] != bit )
+        });
+        if let Some( t ) = target {
+            cages[t].cells.push( cell );
+            cages[t].sum += solution[cell];
+            cages[c].cells.clear();
+            cage_of[cell] = t;
+        }
+    }
+    cages.retain( |cage| !cage.cells.is_empty() );
+    if cages.len() > CAGE_LABELS.len() {
+        return Err( format!( "The grid needs {} cages but only {} can be labelled.", cages.len(), CAGE_LABELS.len() ) );
+    }
+    Ok(cages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{grid, SOLUTION};
+
+    fn bits( digits: &[usize] ) -> usize {
+        digits.iter().fold( 0, |bits, &d| bits | NUM_TO_BITMAP[d] )
+    }
+
+    #[test]
+    fn allowed_bits_follow_the_cage_sums() {
+        assert_eq!( allowed_bits( 2, 3, 0 ), bits( &[1, 2] ) );
+        assert_eq!( allowed_bits( 2, 17, 0 ), bits( &[8, 9] ) );
+        assert_eq!( allowed_bits( 2, 10, 0 ), bits( &[1, 2, 3, 4, 6, 7, 8, 9] ) );
+        assert_eq!( allowed_bits( 2, 10, bits( &[1] ) ), bits( &[2, 3, 4, 6, 7, 8] ) );
+        assert_eq!( allowed_bits( 3, 6, bits( &[1] ) ), 0 );
+        assert_eq!( allowed_bits( 1, 10, 0 ), 0 );
+        assert_eq!( allowed_bits( 9, 45, 0 ), bits( &[1, 2, 3, 4, 5, 6, 7, 8, 9] ) );
+        assert_eq!( allowed_bits( 9, 46, 0 ), 0 );
+    }
+
+    #[test]
+    fn cages_parse_and_format_back() {
+        let map = format!( "aab{}", ".".repeat( GRID_SIZE - 3 ) );
+        let cages = parse_cages( &map, &["a=3", "b=9"] ).unwrap();
+        assert_eq!( cages.len(), 2 );
+        assert_eq!( cages[0].cells, vec![ 0, 1 ] );
+        assert_eq!( cages[1].sum, 9 );
+        assert_eq!( format_cages( &cages ), format!( "{} a=3 b=9", map ) );
+    }
+
+    #[test]
+    fn impossible_cages_are_rejected() {
+        let map = format!( "aab{}", ".".repeat( GRID_SIZE - 3 ) );
+        assert!( parse_cages( &map, &["a=3"] ).is_err() );
+        assert!( parse_cages( &map, &["a=2", "b=9"] ).is_err() );
+        assert!( parse_cages( &map, &["a=3", "b=10"] ).is_err() );
+        assert!( parse_cages( &map, &["a=3", "b"] ).is_err() );
+        assert!( parse_cages( &map, &["a=3", "a=4", "b=9"] ).is_err() );
+        assert!( parse_cages( "ab", &["a=1", "b=1"] ).is_err() );
+    }
+
+    #[test]
+    fn random_cages_add_up_over_their_solution() {
+        let solution = grid( SOLUTION );
+        let mut cages = random_cages( &solution ).unwrap();
+        assert!( cages.len() <= CAGE_LABELS.len() );
+        assert!( cages_satisfied( &cages, &solution ) );
+        let cage_of = cage_index( &cages, GRID_SIZE );
+        for (c, cage) in cages.iter().enumerate() {
+            assert!( cage.cells.iter().all( |&cell| cage_of[cell] == c ) );
+        }
+        cages[0].sum += 1;
+        assert!( !cages_satisfied( &cages, &solution ) );
+    }
+
+    #[test]
+    fn cages_with_repeated_digits_are_not_satisfied() {
+        let solution = grid( SOLUTION );
+        // the first two cells of the second row are 2 and 8
+        let cages = vec![ Cage { sum: 10, cells: vec![ 9, 10 ] } ];
+        assert!( cages_satisfied( &cages, &solution ) );
+        let mut repeated = solution;
+        repeated[10] = 2;
+        let cages = vec![ Cage { sum: 4, cells: vec![ 9, 10 ] } ];
+        assert!( !cages_satisfied( &cages, &repeated ) );
+    }
+}
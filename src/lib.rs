@@ -0,0 +1,2120 @@
+use clap::{Arg, App};
+use std::time::{Instant};
+use rand::Rng;
+use std::io::{self, BufReader, BufWriter};
+use std::io::prelude::*;
+use std::fs;
+use std::fs::File;
+use std::path::Path;
+use console::style;
+use console::{Key, Term};
+use std::fmt;
+
+mod clues;
+pub mod ffi;
+pub mod hints;
+mod jigsaw;
+mod killer;
+mod output;
+mod parity;
+mod pdf;
+pub mod play;
+mod relations;
+mod render;
+mod rpc;
+mod samurai;
+mod server;
+pub mod trace;
+mod units;
+use clues::{Clue, ClueSpec};
+use hints::Hint;
+use killer::Cage;
+use relations::Link;
+use trace::Recorder;
+use render::{ExportFormat, ExportItem, GridPiece, Markings};
+use units::{Board, Constraints};
+use output::LinesWriter;
+use std::str::FromStr;
+
+const GRID_BLCK: usize = 3;
+const GRID_SQRT: usize = GRID_BLCK * GRID_BLCK;
+const GRID_SIZE: usize = GRID_SQRT * GRID_SQRT;
+const NUM_TO_BITMAP: [usize;26] = [
+    0b_0000000000000000000000000, 
+    0b_0000000000000000000000001, 
+    0b_0000000000000000000000010, 
+    0b_0000000000000000000000100, 
+    0b_0000000000000000000001000, 
+    0b_0000000000000000000010000, 
+    0b_0000000000000000000100000, 
+    0b_0000000000000000001000000, 
+    0b_0000000000000000010000000, 
+    0b_0000000000000000100000000,
+    0b_0000000000000001000000000,
+    0b_0000000000000010000000000,
+    0b_0000000000000100000000000,
+    0b_0000000000001000000000000,
+    0b_0000000000010000000000000,
+    0b_0000000000100000000000000,
+    0b_0000000001000000000000000,
+    0b_0000000010000000000000000,
+    0b_0000000100000000000000000,
+    0b_0000001000000000000000000,
+    0b_0000010000000000000000000,
+    0b_0000100000000000000000000,
+    0b_0001000000000000000000000,
+    0b_0010000000000000000000000,
+    0b_0100000000000000000000000,
+    0b_1000000000000000000000000,
+];
+const ALL_DIGITS: usize = ( 1 << GRID_SQRT ) - 1;
+// random fills of most variants finish within a few thousand nodes, but some jigsaw
+// layouts have no solution at all and non-consecutive fills now and then wander
+// into searches of millions of nodes, so for those two a fill that gets this far
+// is given up on and the generator starts again
+const RANDOM_NODE_LIMIT: usize = 100_000;
+const GENERATE_ATTEMPTS: usize = 200;
+const DEADLINE_CHECK_NODES: usize = 1024;
+const GRADE_MEDIUM: usize = 35;
+const GRADE_HARD: usize = 47;
+const NUM_TO_TEXT: [char;17] = ['.','1','2','3','4','5','6','7','8','9','A','B','C','D','E','F','0'];
+
+// the cells orthogonally adjacent to a cell
+fn neighbours( pos: usize ) -> Vec<usize> {
+    let y = pos / GRID_SQRT;
+    let x = pos % GRID_SQRT;
+    let mut cells = Vec::with_capacity( 4 );
+    if y > 0 { cells.push( pos - GRID_SQRT ); }
+    if y < GRID_SQRT-1 { cells.push( pos + GRID_SQRT ); }
+    if x > 0 { cells.push( pos - 1 ); }
+    if x < GRID_SQRT-1 { cells.push( pos + 1 ); }
+    cells
+}
+
+// the easiest deductions up to the next digit to place in a partly filled grid,
+// given as a puzzle line of `variant` with its dots or signs line as `side` where
+// the variant needs one
+pub fn hint( variant: &str, line: &str, side: Option<&str> ) -> Result<Vec<Hint>, String> {
+    let variant = variant.parse::<Variant>()?;
+    let mut sudoku = Sudoku::new( AppOptions { variant, ..AppOptions::default() } );
+    sudoku.initialize_with_line( line, side )?;
+    sudoku.hint()
+}
+
+// the command line program, the binary only calls this
+pub fn cli() {
+    // program start //
+    let app = App::new("SUDOKU CLI Solver & Generator")
+        .version("0.1.0")
+        .author("Andre Sharpe <andre.sharpe@gmail.com>")
+        .about("Solves and generates Sudoku puzzles, but fast!")
+        .arg(Arg::with_name("solve")
+            .short("s")
+            .long("solve")
+            .takes_value(false)
+            .conflicts_with("generate")
+            .help("Solves puzzles in a text file"))
+            .arg(Arg::with_name("generate")
+            .short("g")
+            .long("generate")
+            .takes_value(false)
+            .conflicts_with("solve")
+            .help("Generates puzzles and writes them to a text file"))
+        .arg(Arg::with_name("debug")
+            .short("d")
+            .long("debug")
+            .takes_value(false)
+            .help("Interactively debug the solve steps for puzzles, pausing at each decision when run in a terminal."))
+        .arg(Arg::with_name("play")
+            .long("play")
+            .takes_value(false)
+            .conflicts_with_all(&["generate", "export", "debug"])
+            .help("Plays a puzzle from the file in the terminal"))
+        .arg(Arg::with_name("puzzle")
+            .long("puzzle")
+            .takes_value(true)
+            .help("The puzzle number in the file to play or check, from 1 (default 1)"))
+        .arg(Arg::with_name("candidates")
+            .long("candidates")
+            .takes_value(false)
+            .help("Shows the candidates of every empty cell in debug and hint output"))
+        .arg(Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .takes_value(false)
+            .help("Show solving steps in debug mode."))
+        .arg(Arg::with_name("file")
+            .short("f")
+            .long("file")
+            .takes_value(true)
+            .help("A file containing puzzles, one per line. Defaults to .\\puzzles.txt"))
+        .arg(Arg::with_name("number")
+            .short("n")
+            .conflicts_with("solve")
+            .long("number")
+            .takes_value(true)
+            .help("The number of puzzles to generate, or the first number of puzzles to export. Export takes them all by default"))
+        .arg(Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .takes_value(true)
+            .min_values(0)
+            .max_values(1)
+            .help("A file to write solutions to, one per line. Defaults to <file>.solutions"))
+        .arg(Arg::with_name("append")
+            .long("append")
+            .takes_value(false)
+            .conflicts_with("overwrite")
+            .help("Appends to existing output files. Svg, html and pdf exports are always replaced"))
+        .arg(Arg::with_name("overwrite")
+            .long("overwrite")
+            .takes_value(false)
+            .conflicts_with("append")
+            .help("Replaces existing output files (default)"))
+        .arg(Arg::with_name("resume")
+            .long("resume")
+            .takes_value(false)
+            .conflicts_with("overwrite")
+            .help("Resumes an interrupted run, appending after the puzzles already present in the output files. Solving needs a solutions file given with -o"))
+        .arg(Arg::with_name("export")
+            .short("e")
+            .long("export")
+            .takes_value(true)
+            .possible_values(&["text", "ascii", "svg", "html", "pdf"])
+            .conflicts_with_all(&["solve", "generate"])
+            .help("Exports puzzles in a text file as text, ascii, svg, html or a pdf puzzle book to the output file"))
+        .arg(Arg::with_name("solutions")
+            .long("solutions")
+            .takes_value(false)
+            .requires("export")
+            .help("Includes the solutions when exporting puzzles"))
+        .arg(Arg::with_name("per-page")
+            .long("per-page")
+            .takes_value(true)
+            .requires("export")
+            .help("The number of puzzles per page when exporting html or pdf (2, 4 or 6). Defaults to 6"))
+        .arg(Arg::with_name("title")
+            .long("title")
+            .takes_value(true)
+            .requires("export")
+            .help("The title page heading of a pdf puzzle book"))
+        .arg(Arg::with_name("variant")
+            .long("variant")
+            .takes_value(true)
+            .possible_values(&["standard", "killer", "x", "jigsaw", "hyper", "centre-dot", "asterisk", "anti-knight", "anti-king", "non-consecutive", "kropki", "greater-than", "even-odd", "samurai"])
+            .help("The sudoku variant to solve or generate. Defaults to standard"))
+        .arg(Arg::with_name("difficulty")
+            .long("difficulty")
+            .takes_value(true)
+            .possible_values(&["easy", "medium", "hard", "expert"])
+            .help("The difficulty of generated puzzles, tried up to a number of times"))
+        .arg(Arg::with_name("symmetry")
+            .long("symmetry")
+            .takes_value(true)
+            .possible_values(&["none", "rotational", "mirror"])
+            .help("The symmetry of the givens of generated puzzles. Defaults to none"))
+        .arg(Arg::with_name("serve")
+            .long("serve")
+            .takes_value(false)
+            .conflicts_with_all(&["generate", "export", "play", "check", "hint", "trace", "replay"])
+            .help("Serves solve, count, grade, hint and generate as json over http on localhost"))
+        .arg(Arg::with_name("port")
+            .long("port")
+            .takes_value(true)
+            .requires("serve")
+            .help("The port to serve on (default 8080)"))
+        .arg(Arg::with_name("rpc")
+            .long("rpc")
+            .takes_value(false)
+            .conflicts_with_all(&["serve", "generate", "export", "play", "check", "hint", "trace", "replay"])
+            .help("Answers json-rpc requests for solve, validate, candidates, hint and generate, one per line on stdin"))
+        .arg(Arg::with_name("budget")
+            .long("budget")
+            .takes_value(true)
+            .help("The time in milliseconds a server or rpc request may take (default 5000)"))
+        .arg(Arg::with_name("dots")
+            .long("dots")
+            .takes_value(true)
+            .help("A file of kropki dots, one line per puzzle. Defaults to <file>.dots"))
+        .arg(Arg::with_name("signs")
+            .long("signs")
+            .takes_value(true)
+            .help("A file of greater-than signs, one line per puzzle. Defaults to <file>.signs"))
+        .arg(Arg::with_name("constraints")
+            .long("constraints")
+            .takes_value(true)
+            .conflicts_with("generate")
+            .help("A json file of thermometer, arrow and sandwich clues, one entry per puzzle"))
+        .arg(Arg::with_name("save")
+            .long("save")
+            .takes_value(true)
+            .requires("play")
+            .help("A file to save the game to when playing, resumed from when it exists"))
+        .arg(Arg::with_name("check")
+            .long("check")
+            .takes_value(true)
+            .conflicts_with_all(&["generate", "export", "play", "hint"])
+            .help("Checks a player's grid, given like a puzzle line, against the solution of the chosen puzzle"))
+        .arg(Arg::with_name("trace")
+            .long("trace")
+            .takes_value(true)
+            .conflicts_with_all(&["generate", "export", "play", "check", "hint"])
+            .help("Records the search for every puzzle solved to a trace file"))
+        .arg(Arg::with_name("replay")
+            .long("replay")
+            .takes_value(true)
+            .conflicts_with_all(&["generate", "export", "play", "check", "hint", "trace"])
+            .help("Steps through a trace file forwards and backwards"))
+        .arg(Arg::with_name("hint")
+            .long("hint")
+            .takes_value(true)
+            .conflicts_with_all(&["generate", "export", "play"])
+            .help("Shows the next logical step for a partly filled grid given as a puzzle line"))
+        .arg(Arg::with_name("unique")
+            .short("u")
+            .long("unique")
+            .takes_value(false)
+            .conflicts_with("generate")
+            .help("Checks that every puzzle solved has exactly one solution"));
+
+    let matches = app.get_matches();
+    let filename = String::from( matches.value_of("file").unwrap_or(".\\puzzle.txt") );
+    let export = matches.value_of("export").map( |format| format.parse::<ExportFormat>().unwrap() );
+    let export_solutions = matches.is_present("solutions");
+    let per_page = matches.value_of("per-page").unwrap_or("6").parse::<usize>().unwrap_or(6);
+    let title = String::from( matches.value_of("title").unwrap_or("Sudoku Puzzles") );
+    let variant = matches.value_of("variant").unwrap_or("standard").parse::<Variant>().unwrap();
+    let check_unique = matches.is_present("unique");
+    let constraints_filename = matches.value_of("constraints").map( String::from );
+    let dots_filename = matches.value_of("dots").map( String::from ).unwrap_or_else( || format!( "{}.dots", filename ) );
+    let signs_filename = matches.value_of("signs").map( String::from ).unwrap_or_else( || format!( "{}.signs", filename ) );
+    let output_solutions = matches.is_present("output") && export.is_none();
+    let number = match ( matches.value_of("number"), export ) {
+        ( Some(n), _ ) => n.parse::<usize>().unwrap_or(10),
+        ( None, Some(_) ) => usize::MAX,
+        ( None, None ) => 10,
+    };
+    let debug = matches.is_present("debug");
+    let generate = matches.is_present("generate");
+    let play = matches.is_present("play");
+    let hint = matches.value_of("hint").map( String::from );
+    let save_filename = matches.value_of("save").map( String::from );
+    let check = matches.value_of("check").map( String::from );
+    let trace_filename = matches.value_of("trace").map( String::from );
+    let difficulty = matches.value_of("difficulty").map( |d| d.parse::<Difficulty>().unwrap() );
+    let symmetry = matches.value_of("symmetry").unwrap_or("none").parse::<Symmetry>().unwrap();
+    let serve = matches.is_present("serve");
+    let rpc = matches.is_present("rpc");
+    let port = matches.value_of("port").unwrap_or("8080").parse::<u16>().unwrap_or(8080);
+    let budget_ms = matches.value_of("budget").unwrap_or("5000").parse::<u64>().unwrap_or(5000);
+    let replay_filename = matches.value_of("replay").map( String::from );
+    let puzzle_number = matches.value_of("puzzle").unwrap_or("1").parse::<usize>().unwrap_or(1).max(1);
+    let verbose = matches.is_present("verbose");
+    let candidates = matches.is_present("candidates");
+    let resume = matches.is_present("resume");
+    let append = matches.is_present("append") || resume;
+    let solutions_filename = match ( matches.value_of("output"), export ) {
+        ( Some(path), _ ) => String::from( path ),
+        ( None, Some(format) ) => format!( "{}.{}", filename, format.extension() ),
+        ( None, None ) => format!( "{}.solutions", filename ),
+    };
+    let app_options = AppOptions{ filename, solutions_filename, output_solutions, append, resume, number, debug, generate, play, puzzle_number, save_filename, check, hint, trace_filename, replay_filename, difficulty, symmetry, serve, rpc, port, budget_ms, verbose, candidates, export, export_solutions, per_page, title, variant, check_unique, dots_filename, signs_filename, constraints_filename };
+
+    // stdout carries the responses in rpc mode, so there is no banner or timing
+    if app_options.rpc {
+        if let Err( e ) = rpc::run( &app_options ) { eprintln!( "{}", style( e ).red() ); }
+        return;
+    }
+
+    let banner =
+r" __           _       _          
+/ _\_   _  __| | ___ | | ___   _ 
+\ \| | | |/ _` |/ _ \| |/ / | | |
+_\ \ |_| | (_| | (_) |   <| |_| |
+\__/\__,_|\__,_|\___/|_|\_\\__,_|";
+
+    println!("{}",style(banner).green().bright());
+    println!();
+    println!("{}",style("SUDOKU CLI Solver & Generator").green().bright());
+    println!("{}",style(" made with Rust in 2020").white());
+    println!();
+    println!(" {} {}", style("build version.....").white(), style( format!( "{}x{}", GRID_BLCK, GRID_BLCK ) ).green() );
+    println!(" {} {}", style("mode..............").white(), style( if app_options.generate { "generate" } else if app_options.play { "play" } else if app_options.hint.is_some() { "hint" } else if app_options.check.is_some() { "check" } else if app_options.replay_filename.is_some() { "replay" } else if app_options.serve { "serve" } else if app_options.export.is_some() { "export" } else { "solve" }).green() );
+    if app_options.variant != Variant::Standard { println!(" {} {}", style("variant...........").white(), style(app_options.variant).green()) }
+    if let Some(format) = app_options.export { println!(" {} {}", style("export format.....").white(), style( format!( "{:?}", format ).to_lowercase() ).green()) }
+    if app_options.generate || app_options.export.is_some() { println!(" {} {}", style("number of puzzles.").white(), style(if app_options.number == usize::MAX { "all".to_string() } else { app_options.number.to_string() }).green()) }
+    if app_options.generate { println!(" {} {}", style("symmetry..........").white(), style(app_options.symmetry).green()) }
+    if let (true, Some(difficulty)) = (app_options.generate, app_options.difficulty) { println!(" {} {}", style("difficulty........").white(), style(difficulty).green()) }
+    if app_options.serve { println!(" {} {}", style("address...........").white(), style(format!( "http://127.0.0.1:{}", app_options.port )).green()) }
+    if app_options.serve { println!(" {} {} ms", style("time budget.......").white(), style(app_options.budget_ms).green()) }
+    if app_options.play || app_options.check.is_some() { println!(" {} {}", style("puzzle number.....").white(), style(app_options.puzzle_number ).green()) }
+    if let Some(save) = &app_options.save_filename { println!(" {} {}", style("save file.........").white(), style(save.clone()).green() ) }
+    println!(" {} {}", style("debug.............").white(), style(if app_options.debug { "yes" } else { "no" }).green() );
+    if app_options.debug { println!(" {} {}", style("verbose output....").white(), style(if app_options.verbose { "yes" } else { "no" }).green()) }
+    if app_options.debug || app_options.hint.is_some() { println!(" {} {}", style("candidate view....").white(), style(if app_options.candidates { "yes" } else { "no" }).green()) }
+    println!(" {} {}", style("puzzle file.......").white(), style(app_options.filename.clone()).green() );
+    if app_options.variant == Variant::Kropki { println!(" {} {}", style("dots file.........").white(), style(app_options.dots_filename.clone()).green() ) }
+    if app_options.variant == Variant::GreaterThan { println!(" {} {}", style("signs file........").white(), style(app_options.signs_filename.clone()).green() ) }
+    if let Some(constraints) = &app_options.constraints_filename { println!(" {} {}", style("constraints file..").white(), style(constraints.clone()).green() ) }
+    if let Some(trace) = app_options.trace_filename.as_ref().or( app_options.replay_filename.as_ref() ) { println!(" {} {}", style("trace file........").white(), style(trace.clone()).green() ) }
+    if app_options.output_solutions { println!(" {} {}", style("solutions file....").white(), style(app_options.solutions_filename.clone()).green() ) }
+    if app_options.export.is_some() { println!(" {} {}", style("export file.......").white(), style(app_options.solutions_filename.clone()).green() ) }
+    if app_options.output_solutions || app_options.generate { println!(" {} {}", style("write mode........").white(), style(if app_options.resume { "resume" } else if app_options.append { "append" } else { "overwrite" }).green() ) }
+    println!();
+
+    let now = Instant::now();
+    let count = Sudoku::run( app_options );
+    let millisecs = now.elapsed().as_millis() as f64;
+    println!("{}",style(footer( millisecs/1000.0f64, count )).white());
+    let term = Term::stdout();
+    term.show_cursor().ok();
+}
+
+// the closing line of a run; a run that failed or did no puzzles has no count
+// or speed to show, and one too quick to time has no speed
+fn footer( seconds: f64, count: i32 ) -> String {
+    let elapsed = format!( "Elapsed time: {:.3} seconds.", seconds );
+    if count <= 0 {
+        elapsed
+    } else if seconds <= 0.0 {
+        format!( "{} Puzzles completed: {}.", elapsed, count )
+    } else {
+        format!( "{} Puzzles completed: {}. Peformance: {:.3} puzzles/second.", elapsed, count, f64::from( count )/seconds )
+    }
+}
+
+// how far the interactive debugger runs before it pauses again, or that the
+// player quit and the run stops
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DebugRun {
+    Step,
+    ToBacktrack,
+    ToSolution,
+    Quit,
+}
+
+// the points in the search the debugger can pause at, with the candidates the
+// cell had when the search chose it
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DebugEvent {
+    Try { pos: usize, value: usize, candidates: usize },
+    Backtrack { pos: usize, value: usize, candidates: usize },
+    Solution,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+        write!( f, "{:?}", self )
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str( s: &str ) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "easy" => Ok( Difficulty::Easy ),
+            "medium" => Ok( Difficulty::Medium ),
+            "hard" => Ok( Difficulty::Hard ),
+            "expert" => Ok( Difficulty::Expert ),
+            _ => Err( format!( "Unknown difficulty '{}'. Use easy, medium, hard or expert.", s ) ),
+        }
+    }
+}
+
+// the symmetry of the givens of a generated puzzle
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+enum Symmetry {
+    #[default]
+    None,
+    Rotational,
+    Mirror,
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str( s: &str ) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok( Symmetry::None ),
+            "rotational" => Ok( Symmetry::Rotational ),
+            "mirror" => Ok( Symmetry::Mirror ),
+            _ => Err( format!( "Unknown symmetry '{}'. Use none, rotational or mirror.", s ) ),
+        }
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+        write!( f, "{}", format!( "{:?}", self ).to_lowercase() )
+    }
+}
+
+impl Symmetry {
+    // the cell a given is paired with, turned half way round the centre of the
+    // board or reflected left to right
+    fn partner( self, board: &Board, pos: usize ) -> usize {
+        let ( row, col ) = board.coords( pos );
+        let last = board.side - 1;
+        let partner = match self {
+            Symmetry::None => return pos,
+            Symmetry::Rotational => board.cell_at( last - row, last - col ),
+            Symmetry::Mirror => board.cell_at( row, last - col ),
+        };
+        partner.unwrap_or( pos )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+enum Variant {
+    #[default]
+    Standard,
+    Killer,
+    X,
+    Jigsaw,
+    Hyper,
+    CentreDot,
+    Asterisk,
+    AntiKnight,
+    AntiKing,
+    NonConsecutive,
+    Kropki,
+    GreaterThan,
+    EvenOdd,
+    Samurai,
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str( s: &str ) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "standard" => Ok( Variant::Standard ),
+            "killer" => Ok( Variant::Killer ),
+            "x" | "diagonal" => Ok( Variant::X ),
+            "jigsaw" => Ok( Variant::Jigsaw ),
+            "hyper" | "windoku" => Ok( Variant::Hyper ),
+            "centre-dot" | "center-dot" => Ok( Variant::CentreDot ),
+            "asterisk" => Ok( Variant::Asterisk ),
+            "anti-knight" => Ok( Variant::AntiKnight ),
+            "anti-king" => Ok( Variant::AntiKing ),
+            "non-consecutive" | "nonconsecutive" => Ok( Variant::NonConsecutive ),
+            "kropki" => Ok( Variant::Kropki ),
+            "greater-than" | "futoshiki" => Ok( Variant::GreaterThan ),
+            "even-odd" | "parity" => Ok( Variant::EvenOdd ),
+            "samurai" => Ok( Variant::Samurai ),
+            _ => Err( format!( "Unknown variant '{}'.", s ) ),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+        match self {
+            Variant::CentreDot => write!( f, "centre-dot" ),
+            Variant::AntiKnight => write!( f, "anti-knight" ),
+            Variant::AntiKing => write!( f, "anti-king" ),
+            Variant::NonConsecutive => write!( f, "non-consecutive" ),
+            Variant::GreaterThan => write!( f, "greater-than" ),
+            Variant::EvenOdd => write!( f, "even-odd" ),
+            _ => write!( f, "{}", format!( "{:?}", self ).to_lowercase() ),
+        }
+    }
+}
+
+// a player's grid checked against the solution: the givens with the player's
+// cells, the cells that are wrong and right, and whether it can still be solved
+#[derive(Debug)]
+struct GridCheck {
+    state: Vec<usize>,
+    wrong: Vec<usize>,
+    consistent: Vec<usize>,
+    solvable: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+struct AppOptions {
+    filename: String,
+    solutions_filename: String,
+    output_solutions: bool,
+    append: bool,
+    resume: bool,
+    number: usize,
+    debug: bool,
+    generate: bool,
+    play: bool,
+    puzzle_number: usize,
+    save_filename: Option<String>,
+    check: Option<String>,
+    trace_filename: Option<String>,
+    replay_filename: Option<String>,
+    difficulty: Option<Difficulty>,
+    symmetry: Symmetry,
+    serve: bool,
+    rpc: bool,
+    port: u16,
+    budget_ms: u64,
+    hint: Option<String>,
+    verbose: bool,
+    candidates: bool,
+    export: Option<ExportFormat>,
+    export_solutions: bool,
+    per_page: usize,
+    title: String,
+    variant: Variant,
+    check_unique: bool,
+    dots_filename: String,
+    signs_filename: String,
+    constraints_filename: Option<String>,
+}
+
+
+// the state of a puzzle, one entry per cell of the variant's board
+struct Sudoku {
+    puzzle: Vec<usize>,
+    markup: Vec<usize>,
+    solution: Vec<usize>,
+    solution_count: usize,
+    limit: usize,
+    nodes: usize,
+    regions: [usize; GRID_SIZE],
+    constraints: Constraints,
+    cages: Vec<Cage>,
+    cage_of: Vec<usize>,
+    links: Vec<Link>,
+    links_of: Vec<Vec<usize>>,
+    clues: Vec<Clue>,
+    clues_of: Vec<Vec<usize>>,
+    allowed: Vec<usize>,
+    trace: Option<Recorder<BufWriter<File>>>,
+    debug_run: Option<DebugRun>,
+    depth: usize,
+    deadline: Option<Instant>,
+    timed_out: bool,
+    app_options: AppOptions,
+}
+
+impl fmt::Display for Sudoku {
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+        write!( f, "{}", self.grids_line( &self.solution ) )
+    }
+}
+
+impl Sudoku {
+
+    fn run( app_options: AppOptions ) -> i32 {
+        let generate = app_options.generate;
+        let export = app_options.export;
+        let play = app_options.play;
+        let hint = app_options.hint.clone();
+        let check = app_options.check.clone();
+        let replay = app_options.replay_filename.clone();
+        let serve = app_options.serve;
+        // solving resumes from the solutions file, so without one there is nothing to skip
+        let resume_without_output = app_options.resume && !generate && !app_options.output_solutions;
+        let mut sudoku = Sudoku::new( app_options );
+        let result = if resume_without_output {
+            Err( io::Error::new( io::ErrorKind::InvalidInput, "--resume needs a solutions file, given with -o." ) )
+        } else if let Some( format ) = export {
+            sudoku.export_puzzles_to_file( format )
+        } else if generate {
+            sudoku.generate_puzzles_to_file()
+        } else if play {
+            sudoku.play_puzzle_from_file()
+        } else if let Some( grid ) = hint {
+            sudoku.hint_from_line( &grid )
+        } else if let Some( grid ) = check {
+            sudoku.check_grid_from_line( &grid )
+        } else if let Some( filename ) = replay {
+            sudoku.replay_trace_file( &filename )
+        } else if serve {
+            server::serve( &sudoku.app_options )
+        } else {
+            sudoku.solve_puzzles_from_file()
+        };
+        result.unwrap_or_else( |e| {
+            println!( "{}", style( e ).red() );
+            -1
+        })
+    }
+
+    fn new( app_options: AppOptions ) -> Sudoku {
+        let links = if app_options.variant == Variant::NonConsecutive { relations::non_consecutive_links() } else { Vec::new() };
+        let size = Board::of( app_options.variant ).size;
+        Sudoku {
+            puzzle: vec![ 0; size ],
+            markup: vec![ 0; size ],
+            solution: vec![ 0; size ],
+            solution_count: 0,
+            limit: 1,
+            nodes: 0,
+            regions: jigsaw::standard_regions(),
+            constraints: Constraints::new( app_options.variant, &jigsaw::standard_regions(), &[] ),
+            cages: Vec::new(),
+            cage_of: vec![ killer::NO_CAGE; size ],
+            links_of: relations::link_index( &links, size ),
+            links,
+            clues: Vec::new(),
+            clues_of: vec![ Vec::new(); size ],
+            allowed: vec![ ALL_DIGITS; size ],
+            trace: None,
+            debug_run: None,
+            depth: 0,
+            deadline: None,
+            timed_out: false,
+            app_options,
+        }
+    }
+
+    fn board( &self ) -> &'static Board {
+        Board::of( self.app_options.variant )
+    }
+
+    // a puzzle line is the givens of every grid of the board, followed by any
+    // fields the variant needs; kropki dots and greater-than signs come from a
+    // side file with a line for every puzzle line
+    fn initialize_with_line( &mut self, line: &str, side: Option<&str> ) -> Result<(), String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let puzzle = self.parse_grids( &fields, "A puzzle" )?;
+        let fields = &fields[ self.board().grids.len() - 1.. ];
+        match self.app_options.variant {
+            Variant::Standard | Variant::X | Variant::Hyper | Variant::CentreDot | Variant::Asterisk
+                | Variant::AntiKnight | Variant::AntiKing | Variant::NonConsecutive | Variant::Samurai => {},
+            Variant::Killer => {
+                if fields.len() < 2 { return Err( "A killer puzzle needs a cage map.".to_string() ); }
+                let cages = killer::parse_cages( fields[1], &fields[2..] )?;
+                self.set_cages( cages );
+            },
+            Variant::Jigsaw => {
+                if fields.len() < 2 { return Err( "A jigsaw puzzle needs a region map.".to_string() ); }
+                let regions = jigsaw::parse_regions( fields[1] )?;
+                self.set_regions( regions );
+            },
+            Variant::EvenOdd => {
+                if fields.len() < 2 { return Err( "An even-odd puzzle needs a parity map.".to_string() ); }
+                self.allowed = parity::parse_masks( fields[1], &fields[2..] )?.to_vec();
+            },
+            Variant::Kropki | Variant::GreaterThan => {
+                let side = side.ok_or( format!( "A {} puzzle needs a line in the {} file.", self.app_options.variant, self.side_file_kind() ) )?;
+                self.set_links( relations::parse_links( side, self.app_options.variant == Variant::GreaterThan )? );
+            },
+        }
+        self.initialize_with_array( &puzzle );
+        Ok(())
+    }
+
+    // the values of the grid fields at the start of a line, one field of
+    // GRID_SIZE cells per grid of the board; a cell two grids share may be given
+    // in either of them but not with two different values
+    fn parse_grids( &self, fields: &[&str], what: &str ) -> Result<Vec<usize>, String> {
+        let board = self.board();
+        let grids = board.grids.len();
+        if fields.len() < grids || fields[ ..grids ].iter().any( |field| field.len() != GRID_SIZE ) {
+            return Err( if grids == 1 {
+                format!( "{} must have {} cells.", what, GRID_SIZE )
+            } else {
+                format!( "{} must have {} grids of {} cells.", what, grids, GRID_SIZE )
+            });
+        }
+        let mut values = vec![ 0; board.size ];
+        for (g, field) in fields[ ..grids ].iter().enumerate() {
+            for (pos, &b) in field.as_bytes().iter().enumerate() {
+                let value = if (b'1'..=b'9').contains(&b) {
+                    (b - 48) as usize
+                } else if (b'A'..=b'F').contains(&b) {
+                    (b - 55) as usize
+                } else if b == b'0' {
+                    16
+                } else {
+                    0
+                };
+                let cell = board.grids[g].cells[pos];
+                if value == 0 { continue; }
+                if values[cell] != 0 && values[cell] != value {
+                    return Err( format!( "Grid {} gives a shared cell a different value.", g+1 ) );
+                }
+                values[cell] = value;
+            }
+        }
+        Ok(values)
+    }
+
+    // the values of every grid of the board as fields of GRID_SIZE cells
+    fn grids_line( &self, values: &[usize] ) -> String {
+        let board = self.board();
+        ( 0..board.grids.len() )
+            .map( |g| board.grid_values( values, g ).iter().map( |&value| NUM_TO_TEXT[ value ] ).collect::<String>() )
+            .collect::<Vec<String>>()
+            .join( " " )
+    }
+
+    // the line written in place of a solution for a puzzle without one
+    fn unsolved_line( &self ) -> String {
+        vec![ ".".repeat( GRID_SIZE ); self.board().grids.len() ].join( " " )
+    }
+
+    fn is_puzzle_line( line: &str ) -> bool {
+        line.split_whitespace().next().is_some_and( |givens| givens.len() == GRID_SIZE )
+    }
+
+    // the puzzle in the same format initialize_with_line reads
+    fn puzzle_line( &self ) -> Result<String, String> {
+        let mut line = self.grids_line( &self.puzzle );
+        if self.app_options.variant == Variant::Killer {
+            line += " ";
+            line += &killer::format_cages( &self.cages );
+        }
+        if self.app_options.variant == Variant::Jigsaw {
+            line += " ";
+            line += &jigsaw::format_regions( &self.regions );
+        }
+        if self.app_options.variant == Variant::EvenOdd {
+            line += " ";
+            line += &parity::format_masks( &self.allowed )?;
+        }
+        Ok(line)
+    }
+
+    // the dots or signs in the format of the side file, empty for other variants
+    fn side_line( &self ) -> String {
+        relations::format_links( &self.links )
+    }
+
+    fn side_file_kind( &self ) -> &'static str {
+        if self.app_options.variant == Variant::Kropki { "dots" } else { "signs" }
+    }
+
+    // the file of dots or signs that goes with the puzzle file
+    fn side_filename( &self ) -> Option<&str> {
+        match self.app_options.variant {
+            Variant::Kropki => Some( &self.app_options.dots_filename ),
+            Variant::GreaterThan => Some( &self.app_options.signs_filename ),
+            _ => None,
+        }
+    }
+
+    fn set_links( &mut self, links: Vec<Link> ) {
+        self.links_of = relations::link_index( &links, self.board().size );
+        self.links = links;
+    }
+
+    fn set_clues( &mut self, clues: Vec<Clue> ) {
+        self.clues_of = clues::clue_index( &clues, self.board().size );
+        self.clues = clues;
+    }
+
+    fn set_regions( &mut self, regions: [usize; GRID_SIZE] ) {
+        self.regions = regions;
+        self.update_constraints();
+    }
+
+    fn set_cages( &mut self, cages: Vec<Cage> ) {
+        self.cage_of = killer::cage_index( &cages, self.board().size );
+        self.cages = cages;
+        self.update_constraints();
+    }
+
+    // the units and peers only change with the regions and cages, not per solve
+    fn update_constraints( &mut self ) {
+        let groups: Vec<Vec<usize>> = self.cages.iter().map( |cage| cage.cells.clone() ).collect();
+        self.constraints = Constraints::new( self.app_options.variant, &self.regions, &groups );
+    }
+
+    fn initialize_with_array( &mut self, a_puzzle: &[usize] ) {
+        self.clear();
+        for (pos,&val) in a_puzzle.iter().enumerate() {
+            self.puzzle[ pos ] = val;
+            self.solution[ pos ] = val;
+        }
+    }
+
+    fn clear( &mut self ) {
+        self.solution_count = 0;
+        for pos in 0..self.board().size { self.puzzle[ pos ] = 0; self.solution[ pos ] = 0; self.markup[ pos ] = usize::MAX; }
+    }
+
+    fn do_markup( &mut self ) {
+        for pos in 0..self.board().size {
+            if self.solution[ pos ] == 0 {
+                self.markup[ pos ] = self.invalid_values_as_bits(pos);
+            } else {
+                self.markup[ pos ] = 0; // fill with 1's - all values invalid
+            }
+        }
+    }
+
+    fn set_value_and_markup( &mut self, pos: usize, value: usize ){
+        self.solution[ pos ] = value;
+        let bitmap = NUM_TO_BITMAP[ value ];
+        for n in 0..self.constraints.peers[ pos ].len() {
+            self.markup[ self.constraints.peers[ pos ][ n ] ] |= bitmap;
+        }
+        let c = self.cage_of[ pos ];
+        if c != killer::NO_CAGE {
+            for i in 0..self.cages[c].cells.len() {
+                let cell = self.cages[c].cells[i];
+                if self.solution[ cell ] == 0 {
+                    self.markup[ cell ] |= self.cage_invalid_bits( c );
+                }
+            }
+        }
+        for i in 0..self.links_of[ pos ].len() {
+            let link = self.links[ self.links_of[ pos ][ i ] ];
+            let other = link.other( pos );
+            self.markup[ other ] |= !link.allowed( other, bitmap ) & ALL_DIGITS;
+        }
+        // only the clues through the placed cell can have lost options, and only for their own cells
+        for i in 0..self.clues_of[ pos ].len() {
+            let c = self.clues_of[ pos ][ i ];
+            for cell in self.clues[ c ].cells() {
+                if self.solution[ cell ] == 0 {
+                    self.markup[ cell ] |= self.clue_invalid_bits( c, cell );
+                }
+            }
+        }
+        self.markup[ pos ] = usize::MAX;
+    }
+
+    // values ruled out for the empty cells of a cage by the digits already in
+    // it and the combinations that can still make up its sum
+    fn cage_invalid_bits( &self, c: usize ) -> usize {
+        let cage = &self.cages[c];
+        let mut used = 0;
+        let mut total = 0;
+        let mut empty = 0;
+        for &cell in cage.cells.iter() {
+            let value = self.solution[ cell ];
+            if value == 0 { empty += 1; } else { used |= NUM_TO_BITMAP[ value ]; total += value; }
+        }
+        if total > cage.sum { return ALL_DIGITS; }
+        !killer::allowed_bits( empty, cage.sum - total, used ) & ALL_DIGITS
+    }
+
+    // values that would leave thermometer, arrow or sandwich `c` through the cell impossible
+    #[allow(clippy::needless_range_loop)]
+    fn clue_invalid_bits( &self, c: usize, pos: usize ) -> usize {
+        let mut bits = 0;
+        for value in 1..GRID_SQRT+1 {
+            if !self.clues[c].feasible_with( &self.solution, pos, value ) {
+                bits |= NUM_TO_BITMAP[ value ];
+            }
+        }
+        bits
+    }
+
+    // narrows the markup of linked cells against each other's candidates until
+    // nothing changes, a digit stays only if some candidate of the other cell fits it
+    fn prune_links( &mut self ) {
+        if self.links.is_empty() { return; }
+        loop {
+            let mut changed = false;
+            for l in 0..self.links.len() {
+                let link = self.links[ l ];
+                for &(cell, other) in [ ( link.a, link.b ), ( link.b, link.a ) ].iter() {
+                    if self.solution[ cell ] != 0 { continue; }
+                    let candidates = if self.solution[ other ] != 0 {
+                        NUM_TO_BITMAP[ self.solution[ other ] ]
+                    } else {
+                        !self.markup[ other ] & ALL_DIGITS
+                    };
+                    let markup = self.markup[ cell ] | ( !link.allowed( cell, candidates ) & ALL_DIGITS );
+                    if markup != self.markup[ cell ] {
+                        self.markup[ cell ] = markup;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed { break; }
+        }
+    }
+
+    // the lines of the side file, kept as they are so they stay in step with the puzzles
+    fn read_side_lines( &self ) -> io::Result<Vec<String>> {
+        let filename = match self.side_filename() {
+            Some( filename ) => filename,
+            None => return Ok( Vec::new() ),
+        };
+        match fs::read_to_string( filename ) {
+            Ok( contents ) => Ok( contents.lines().map( String::from ).collect() ),
+            Err( e ) if e.kind() == io::ErrorKind::NotFound => Ok( Vec::new() ),
+            Err( e ) => Err( e ),
+        }
+    }
+
+    // clue cells are named by row and column of one grid, so a board of several
+    // grids has no constraint file
+    fn read_clue_specs( &self ) -> io::Result<Vec<ClueSpec>> {
+        match &self.app_options.constraints_filename {
+            Some( _ ) if self.board().grids.len() > 1 => Err( io::Error::new( io::ErrorKind::InvalidInput,
+                format!( "A constraint file is only for a {}x{} board, not a {} one.", GRID_SQRT, GRID_SQRT, self.app_options.variant ) ) ),
+            Some( filename ) => clues::parse_clue_file( &fs::read_to_string( filename )? )
+                .map_err( |e| io::Error::new( io::ErrorKind::InvalidData, e ) ),
+            None => Ok( Vec::new() ),
+        }
+    }
+
+    // the clues for the puzzle at `index` when a constraint file is in use
+    fn load_clues( &mut self, specs: &[ClueSpec], index: usize ) -> Result<(), String> {
+        if self.app_options.constraints_filename.is_none() { return Ok(()); }
+        let spec = specs.get( index ).ok_or( "The constraint file has no entry for this puzzle." )?;
+        let clues = clues::parse_clues( spec )?;
+        self.set_clues( clues );
+        Ok(())
+    }
+
+    fn solve_puzzles_from_file( &mut self ) -> io::Result<i32> {
+        let filename = self.app_options.filename.clone();
+        let puzzle_file = File::open( &filename )?;
+        let puzzle_file = BufReader::new( puzzle_file );
+        let side = self.read_side_lines()?;
+        let specs = self.read_clue_specs()?;
+        let mut index = 0;
+        let mut result = 0;
+
+        let mut skip = 0;
+        if self.app_options.resume && self.app_options.output_solutions {
+            skip = Sudoku::count_lines_in_file( &self.app_options.solutions_filename )?;
+            if skip > 0 { println!( "Resuming after {} solved puzzles.", skip ); }
+        }
+        let mut solution_file = if self.app_options.output_solutions {
+            Some( self.open_lines_writer( &self.app_options.solutions_filename )? )
+        } else {
+            None
+        };
+        let stepping = self.app_options.debug && Term::stdout().is_term();
+        let mut trace_file = match &self.app_options.trace_filename {
+            Some( filename ) => Some( Recorder::new( BufWriter::new( File::create( filename )? ), self.app_options.variant )? ),
+            None => None,
+        };
+
+        for line in puzzle_file.lines() {
+            let str_puzzle = line?;
+            if Sudoku::is_puzzle_line( &str_puzzle ) {
+                index += 1;
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                let loaded = self.load_clues( &specs, index-1 )
+                    .and_then( |_| self.initialize_with_line( &str_puzzle, side.get( index-1 ).map( String::as_str ) ) );
+                if let Err( e ) = loaded {
+                    println!( "Puzzle #{} is not valid: {}", result+1, e );
+                    if let Some( writer ) = solution_file.as_mut() {
+                        writer.write_line( &self.unsolved_line() )?;
+                    }
+                    result += 1;
+                    continue;
+                }
+                if self.app_options.debug {
+                    self.solution_count = 1;
+                    self.display( format!("Attempting puzzle #{}...", result+1) );
+                    self.solution_count = 0;
+                }
+                // every puzzle starts out stepping, even after the last one was run through
+                if stepping { self.debug_run = Some( DebugRun::Step ); }
+                // only the first search of a puzzle is recorded, not the one checking it is unique
+                if let Some( mut recorder ) = trace_file.take() {
+                    let line = self.puzzle_line().map_err( |e| io::Error::new( io::ErrorKind::InvalidData, e ) )?;
+                    recorder.puzzle( index, &line, self.side_filename().map( |_| self.side_line() ).as_deref() );
+                    self.trace = Some( recorder );
+                }
+                self.solve_fast( 1 );
+                if let Some( mut recorder ) = self.trace.take() {
+                    recorder.finish()?;
+                    trace_file = Some( recorder );
+                }
+                if self.debug_run == Some( DebugRun::Quit ) {
+                    println!( "Stopped at puzzle #{}.", result+1 );
+                    break;
+                }
+                if self.solution_count == 1 {
+                    if self.app_options.debug {
+                        self.display( format!("...solved puzzle #{}", result+1) );
+                    }
+                } else {
+                    println!( "There is no solution for puzzle #{}.", result+1);
+                }
+                if self.app_options.check_unique && self.solution_count == 1 && !self.has_unique_solution() {
+                    println!( "Puzzle #{} has more than one solution.", result+1);
+                }
+                if let Some( writer ) = solution_file.as_mut() {
+                    let s_puzzle = if self.solution_count == 0 {
+                        self.unsolved_line()
+                    } else {
+                        self.to_string()
+                    };
+                    writer.write_line( &s_puzzle )?;
+                }
+                result += 1;
+            }
+        }
+        if let Some( writer ) = solution_file { writer.finish()?; }
+        if let Some( mut recorder ) = trace_file { recorder.flush()?; }
+        Ok(result)
+    }
+
+    fn generate_puzzles_to_file( &mut self ) -> io::Result<i32> {
+        let mut number = self.app_options.number;
+        let mut result = 0;
+
+        if self.app_options.resume {
+            let existing = Sudoku::read_lines_from_file( &self.app_options.filename )?;
+            number = number.saturating_sub( existing.len() );
+            if !existing.is_empty() { println!( "Resuming after {} generated puzzles.", existing.len() ); }
+            // the side line goes out before its puzzle, so a run that stopped between
+            // the two left one too many and it is cut off to keep the files in step
+            let mut side = self.read_side_lines()?;
+            if let Some( filename ) = self.side_filename() {
+                if side.len() > existing.len() {
+                    side.truncate( existing.len() );
+                    let kept: String = side.iter().map( |line| format!( "{}\n", line ) ).collect();
+                    output::write_atomically( filename, kept.as_bytes() )?;
+                }
+            }
+            if self.app_options.output_solutions {
+                // the run may have stopped between writing a puzzle and its solution
+                let solved = Sudoku::count_lines_in_file( &self.app_options.solutions_filename )?;
+                let mut solution_file = self.open_lines_writer( &self.app_options.solutions_filename )?;
+                for (i, str_puzzle) in existing.into_iter().enumerate().skip( solved ) {
+                    self.initialize_with_line( &str_puzzle, side.get( i ).map( String::as_str ) ).ok();
+                    self.solve_fast( 1 );
+                    solution_file.write_line( &self.to_string() )?;
+                }
+                solution_file.finish()?;
+            }
+        }
+
+        let mut puzzle_file = self.open_lines_writer( &self.app_options.filename )?;
+        let mut solution_file = if self.app_options.output_solutions {
+            Some( self.open_lines_writer( &self.app_options.solutions_filename )? )
+        } else {
+            None
+        };
+        let mut side_file = match self.side_filename() {
+            Some( filename ) => Some( self.open_lines_writer( filename )? ),
+            None => None,
+        };
+
+        for i in 0..number {
+            self.generate();
+            if let Some( difficulty ) = self.app_options.difficulty {
+                let graded = self.grade();
+                if graded != difficulty {
+                    println!( "No {} puzzle found in {} tries, puzzle #{} is {}.", difficulty, GENERATE_ATTEMPTS, i+1, graded );
+                }
+            }
+            if self.app_options.debug { 
+                self.display( format!("...generated puzzle {} of {}:", i+1, number ) );
+            }
+            if let Some( writer ) = side_file.as_mut() {
+                writer.write_line( &self.side_line() )?;
+            }
+            puzzle_file.write_line( &self.puzzle_line().map_err( |e| io::Error::new( io::ErrorKind::InvalidData, e ) )? )?;
+            if let Some( writer ) = solution_file.as_mut() {
+                self.solve_fast( 1 );
+                writer.write_line( &self.to_string() )?;
+            }
+            result += 1;
+        }
+        puzzle_file.finish()?;
+        if let Some( writer ) = solution_file { writer.finish()?; }
+        if let Some( writer ) = side_file { writer.finish()?; }
+        Ok(result)
+    }
+
+    // prints the next logical step for a partly filled grid, side files and
+    // constraints come from their first line or entry
+    fn hint_from_line( &mut self, line: &str ) -> io::Result<i32> {
+        let side = self.read_side_lines()?;
+        let specs = self.read_clue_specs()?;
+        let hints = self.load_clues( &specs, 0 )
+            .and_then( |_| self.initialize_with_line( line, side.first().map( String::as_str ) ) )
+            .and_then( |_| self.hint() )
+            .map_err( |e| io::Error::new( io::ErrorKind::InvalidData, e ) )?;
+        if !self.app_options.candidates {
+            Sudoku::display_pieces( "Grid:".to_string(), self.grid_pieces_for( &self.puzzle, &self.solution ), false );
+        }
+        if hints.is_empty() {
+            if self.app_options.candidates { self.display_candidates( "Candidates:".to_string(), &self.candidates(), None ); }
+            println!( "{}", style( "No hint: the grid needs more than the techniques known, or trial and error." ).yellow() );
+            return Ok(0);
+        }
+        if self.app_options.candidates {
+            // the hints applied to the candidates, what they remove in red
+            let before = self.candidates();
+            let mut after = before.clone();
+            for hint in hints.iter() {
+                if let Some( (pos, value) ) = hint.placement() { after[ pos ] = NUM_TO_BITMAP[ value ]; }
+                for &(pos, value) in hint.eliminations.iter() { after[ pos ] &= !NUM_TO_BITMAP[ value ]; }
+            }
+            self.display_candidates( "Candidates:".to_string(), &after, Some( &before ) );
+        }
+        for hint in hints.iter() {
+            println!( "{} {}", style( "Hint:" ).white(), style( hint ).green() );
+        }
+        if hints.last().is_some_and( |hint| hint.placement().is_none() ) {
+            println!( "{}", style( "No digit to place yet: the grid needs more than the techniques known, or trial and error." ).yellow() );
+        }
+        Ok(1)
+    }
+
+    // the easiest deductions left in the loaded grid up to the next placement, from
+    // the candidates the solver marks up; errors when filled cells clash or the
+    // grid is stuck
+    fn hint( &mut self ) -> Result<Vec<Hint>, String> {
+        self.clash()?;
+        self.do_markup();
+        self.prune_links();
+        hints::next_hints( self.app_options.variant, &self.solution, &self.candidates(), &self.constraints.units )
+    }
+
+    // errors on the first two filled cells that see each other and hold the same digit
+    fn clash( &self ) -> Result<(), String> {
+        let board = self.board();
+        for pos in 0..board.size {
+            let value = self.solution[ pos ];
+            if let Some( &p ) = self.constraints.peers[ pos ].iter().find( |&&p| value != 0 && self.solution[ p ] == value ) {
+                return Err( format!( "{} and {} both hold {}.", board.cell_name( pos ), board.cell_name( p ), value ) );
+            }
+        }
+        Ok(())
+    }
+
+    // steps through a trace file in the terminal, laid out for the variant it was recorded with
+    fn replay_trace_file( &mut self, filename: &str ) -> io::Result<i32> {
+        let text = fs::read_to_string( filename )?;
+        let invalid = |e: String| io::Error::new( io::ErrorKind::InvalidData, e );
+        let ( variant, traces ) = trace::parse_traces( &text ).map_err( invalid )?;
+        if traces.is_empty() { return Err( invalid( "The trace has no puzzles.".to_string() ) ); }
+        let mut replayer = Sudoku::new( AppOptions { variant, ..self.app_options.clone() } );
+        trace::replay( &mut replayer, &traces )?;
+        Ok( traces.len() as i32 )
+    }
+
+    // loads the chosen puzzle line and solves it, returning its number
+    fn load_puzzle_number( &mut self ) -> io::Result<usize> {
+        let number = self.app_options.puzzle_number;
+        let lines = Sudoku::read_lines_from_file( &self.app_options.filename )?;
+        let line = lines.get( number-1 ).ok_or_else( || io::Error::new( io::ErrorKind::InvalidInput,
+            format!( "The file has {} puzzles, there is no puzzle #{}.", lines.len(), number ) ) )?;
+        let side = self.read_side_lines()?;
+        let specs = self.read_clue_specs()?;
+        self.load_clues( &specs, number-1 )
+            .and_then( |_| self.initialize_with_line( line, side.get( number-1 ).map( String::as_str ) ) )
+            .map_err( |e| io::Error::new( io::ErrorKind::InvalidData, format!( "Puzzle #{} is not valid: {}", number, e ) ) )?;
+        self.solve_fast( 1 );
+        if self.solution_count != 1 {
+            return Err( io::Error::new( io::ErrorKind::InvalidData, format!( "Puzzle #{} has no solution.", number ) ) );
+        }
+        Ok(number)
+    }
+
+    // compares a player's grid with the solution of the loaded puzzle: which of
+    // the player's cells are wrong, which agree, and whether the givens plus the
+    // player's cells can still be solved
+    fn check_grid( &mut self, grid: &[usize] ) -> Result<GridCheck, String> {
+        let solution = self.solution.clone();
+        let puzzle = self.puzzle.clone();
+        let board = self.board();
+        let mut state = puzzle.clone();
+        let mut wrong = Vec::new();
+        let mut consistent = Vec::new();
+        for pos in 0..board.size {
+            let value = grid[pos];
+            if puzzle[pos] != 0 {
+                if value != 0 && value != puzzle[pos] {
+                    return Err( format!( "The grid changes the given {} at {}.", puzzle[pos], board.cell_name( pos ) ) );
+                }
+                continue;
+            }
+            state[pos] = value;
+            if value == 0 { continue; }
+            if value == solution[pos] { consistent.push( pos ); } else { wrong.push( pos ); }
+        }
+
+        // the solver does not look at filled cells again, so clashes are caught first
+        let clash = ( 0..board.size ).any( |pos| state[pos] != 0 && self.constraints.peers[pos].iter().any( |&p| state[p] == state[pos] ) );
+        let solvable = !clash && {
+            self.initialize_with_array( &state );
+            self.solve_fast( 1 );
+            self.solution_count == 1
+        };
+        self.initialize_with_array( &puzzle );
+        self.solution = solution;
+        Ok( GridCheck { state, wrong, consistent, solvable } )
+    }
+
+    // checks a player's grid against the chosen puzzle and shows the result;
+    // returns 1 for the one puzzle checked
+    fn check_grid_from_line( &mut self, line: &str ) -> io::Result<i32> {
+        let number = self.load_puzzle_number()?;
+        let unique = self.has_unique_solution();
+        let puzzle = self.puzzle.clone();
+        let board = self.board();
+        let invalid = |e: String| io::Error::new( io::ErrorKind::InvalidData, e );
+        let grid = self.parse_grids( &line.split_whitespace().collect::<Vec<&str>>(), "The grid to check" ).map_err( invalid )?;
+        let check = self.check_grid( &grid ).map_err( invalid )?;
+
+        let cells = |list: &[usize]| list.iter().map( |&pos| format!( "{}={}", board.cell_name( pos ), check.state[pos] ) ).collect::<Vec<String>>().join( " " );
+        Sudoku::display_pieces( format!( "Puzzle #{} as played:", number ), self.grid_pieces_for( &puzzle, &check.state ), false );
+        if !unique { println!( "{}", style( "The puzzle has more than one solution, checking against the first found." ).yellow() ); }
+        println!( "{} {} {}", style( "Wrong...........").white(), style( check.wrong.len() ).red(), style( cells( &check.wrong ) ).red() );
+        println!( "{} {} {}", style( "Consistent......").white(), style( check.consistent.len() ).green(), style( cells( &check.consistent ) ).green() );
+        println!( "{} {}", style( "Still solvable..").white(), if check.solvable { style( "yes" ).green() } else { style( "no" ).red() } );
+        Ok(1)
+    }
+
+    // loads the chosen puzzle line, solves it to check entries against and hands it to the player
+    fn play_puzzle_from_file( &mut self ) -> io::Result<i32> {
+        let number = self.load_puzzle_number()?;
+        let solution = self.solution.clone();
+        let save = self.app_options.save_filename.clone();
+        let mut game = match &save {
+            Some( filename ) if Path::new( filename ).exists() => {
+                let json = fs::read_to_string( filename )?;
+                play::Game::from_json( &json, self.board(), &self.puzzle ).map_err( |e| io::Error::new( io::ErrorKind::InvalidData, e ) )?
+            },
+            _ => play::Game::new( self.puzzle.clone() ),
+        };
+        let solved = play::play( self, &mut game, &format!( "Puzzle #{}", number ), &solution, save.as_deref() )?;
+        Term::stdout().show_cursor().ok();
+        Ok( if solved { 1 } else { 0 } )
+    }
+
+    fn export_puzzles_to_file( &mut self, format: ExportFormat ) -> io::Result<i32> {
+        let puzzles = Sudoku::read_lines_from_file( &self.app_options.filename )?;
+        let side = self.read_side_lines()?;
+        let specs = self.read_clue_specs()?;
+        let mut items = Vec::new();
+        if format == ExportFormat::Pdf && !pdf::PAGE_LAYOUTS.contains( &self.app_options.per_page ) {
+            return Err( io::Error::new( io::ErrorKind::InvalidInput, "A pdf puzzle book has 2, 4 or 6 puzzles per page." ) );
+        }
+        for (i, str_puzzle) in puzzles.into_iter().take( self.app_options.number ).enumerate() {
+            let loaded = self.load_clues( &specs, i )
+                .and_then( |_| self.initialize_with_line( &str_puzzle, side.get( i ).map( String::as_str ) ) );
+            if let Err( e ) = loaded {
+                println!( "Puzzle #{} is not valid: {}", i+1, e );
+                continue;
+            }
+            let difficulty = Some( self.grade() );
+            let mut solution = None;
+            // the answer key is part of every puzzle book
+            if self.app_options.export_solutions || format == ExportFormat::Pdf {
+                self.solve_fast( 1 );
+                if self.solution_count == 1 {
+                    solution = Some( self.solution.clone() );
+                } else {
+                    println!( "There is no solution for puzzle #{}.", i+1 );
+                }
+            }
+            items.push( ExportItem { number: i+1, puzzle: self.puzzle.clone(), solution, difficulty, markings: self.markings() } );
+        }
+
+        let invalid = |e: String| io::Error::new( io::ErrorKind::InvalidData, e );
+        let document = match format {
+            ExportFormat::Text => render::export_text( &items, self.board(), &self.constraints, &render::UNICODE_BOX ).map_err( invalid )?.into_bytes(),
+            ExportFormat::Ascii => render::export_text( &items, self.board(), &self.constraints, &render::ASCII_BOX ).map_err( invalid )?.into_bytes(),
+            ExportFormat::Svg => render::export_svg( &items, self.board(), &self.constraints ).into_bytes(),
+            ExportFormat::Html => render::export_html( &items, self.board(), &self.constraints, self.app_options.per_page ).into_bytes(),
+            ExportFormat::Pdf => pdf::puzzle_book( &self.app_options.title, &items, self.board(), &self.constraints, self.app_options.per_page ),
+        };
+        // text can go on the end of an earlier export, the other formats are whole documents
+        let filename = &self.app_options.solutions_filename;
+        match format {
+            ExportFormat::Text | ExportFormat::Ascii if self.app_options.append => {
+                let mut writer = LinesWriter::open( filename, true )?;
+                writer.write_line( String::from_utf8_lossy( &document ).trim_end_matches( '\n' ) )?;
+                writer.finish()?;
+            },
+            _ => output::write_atomically( filename, &document )?,
+        }
+        Ok( items.len() as i32 )
+    }
+
+    // opens an output file honouring the append/overwrite option, --resume always appends
+    fn open_lines_writer( &self, filename: &str ) -> io::Result<LinesWriter> {
+        LinesWriter::open( filename, self.app_options.append )
+    }
+
+    fn read_lines_from_file( filename: &str ) -> io::Result<Vec<String>> {
+        let file = match File::open( filename ) {
+            Ok( file ) => file,
+            Err( e ) if e.kind() == io::ErrorKind::NotFound => return Ok( Vec::new() ),
+            Err( e ) => return Err( e ),
+        };
+        let mut lines = Vec::new();
+        for line in BufReader::new( file ).lines() {
+            let line = line?;
+            if Sudoku::is_puzzle_line( &line ) { lines.push( line ); }
+        }
+        Ok(lines)
+    }
+
+    fn count_lines_in_file( filename: &str ) -> io::Result<usize> {
+        Ok( Sudoku::read_lines_from_file( filename )?.len() )
+    }
+
+    // cells drawn in a different colour to show the extra units of a variant
+    fn shaded_cells( &self ) -> Vec<bool> {
+        self.constraints.shaded_cells()
+    }
+
+    // whether `values` fill the board so that every unit holds each digit once,
+    // no cell repeats a digit of its peers and every mask, cage, link and clue holds
+    pub(crate) fn solves( &self, values: &[usize] ) -> bool {
+        let filled = values.iter().enumerate().all( |(pos, &value)| value != 0 && self.allowed[ pos ] & NUM_TO_BITMAP[ value ] != 0 );
+        filled
+            && self.constraints.units.iter().all( |unit| unit.iter().fold( 0, |seen, &p| seen | NUM_TO_BITMAP[ values[p] ] ) == ALL_DIGITS )
+            && values.iter().enumerate().all( |(pos, &value)| self.constraints.peers[ pos ].iter().all( |&p| values[p] != value ) )
+            && killer::cages_satisfied( &self.cages, values )
+            && relations::links_satisfied( &self.links, values )
+            && clues::clues_satisfied( &self.clues, values )
+    }
+
+    // the digits still possible in each empty cell, from the markup
+    fn candidates( &self ) -> Vec<usize> {
+        let mut candidates = vec![ 0; self.board().size ];
+        for (pos, candidate) in candidates.iter_mut().enumerate() {
+            if self.solution[ pos ] == 0 { *candidate = !self.markup[ pos ] & ALL_DIGITS; }
+        }
+        candidates
+    }
+
+    // prints the grid with the candidates of every empty cell, those that are in
+    // `previous` but no longer candidates shown in red; a board of several grids
+    // is printed a grid at a time
+    fn display_candidates( &self, heading: String, candidates: &[usize], previous: Option<&[usize]> ) {
+        let board = self.board();
+        if board.grids.len() == 1 {
+            let pieces = render::candidate_grid_pieces( &self.puzzle, &self.solution, &self.shaded_cells(), candidates, previous );
+            Sudoku::display_pieces( heading, pieces, false );
+            return;
+        }
+        println!( "{}", style( heading ).white() );
+        for g in 0..board.grids.len() {
+            let grid = |values: &[usize]| board.grid_values( values, g );
+            let previous = previous.map( grid );
+            let pieces = render::candidate_grid_pieces( &grid( &self.puzzle ), &grid( &self.solution ), &[false; GRID_SIZE], &grid( candidates ), previous.as_ref().map( |p| &p[..] ) );
+            Sudoku::display_pieces( format!( "Grid {}:", g+1 ), pieces, false );
+        }
+    }
+
+    fn display( &self, heading: String ) {
+        let pieces = self.grid_pieces_for( &self.puzzle, &self.solution );
+        Sudoku::display_pieces( heading, pieces, self.solution_count != self.limit );
+    }
+
+    // the grid of this variant laid out with `values` in its cells
+    // the regions, cages, dots or signs, clues and parity marks of the loaded
+    // puzzle for an export to draw; non-consecutive links have nothing to show
+    fn markings( &self ) -> Markings {
+        Markings {
+            regions: if self.app_options.variant == Variant::Jigsaw { Some( self.regions ) } else { None },
+            cages: self.cages.clone(),
+            links: self.links.iter().filter( |link| link.symbol().is_some() ).copied().collect(),
+            clues: self.clues.clone(),
+            masks: self.allowed.clone(),
+        }
+    }
+
+    fn grid_pieces_for( &self, puzzle: &[usize], values: &[usize] ) -> Vec<GridPiece> {
+        let dots: Vec<(usize, usize, char)> = self.links.iter()
+            .filter_map( |link| link.symbol().map( |symbol| ( link.a, link.b, symbol ) ) )
+            .collect();
+        if self.app_options.variant == Variant::Jigsaw || !dots.is_empty() {
+            render::region_grid_pieces( puzzle, values, &self.shaded_cells(), &self.regions, &dots, &render::HEAVY_BOX )
+        } else {
+            render::board_pieces( self.board(), puzzle, values, &self.shaded_cells(), &render::UNICODE_BOX )
+        }
+    }
+
+    // prints a laid out grid; `rewind` moves the cursor back up so the next
+    // display draws over it
+    fn display_pieces( heading: String, pieces: Vec<GridPiece>, rewind: bool ) {
+        let term = Term::stdout();
+        term.hide_cursor().ok();
+        println!( "{}", style( heading ).white() ) ; 
+
+        let lines = pieces.iter().filter( |piece| matches!( piece, GridPiece::EndOfLine ) ).count();
+        for piece in pieces {
+            match piece {
+                GridPiece::Border( s ) => print!( "{}", style( s ).green() ),
+                GridPiece::Cell { value, given: false, shaded: false } => print!(" {} ", style( NUM_TO_TEXT[ value ] ).yellow()),
+                GridPiece::Cell { value, given: true, shaded: false } => print!(" {} ", style( NUM_TO_TEXT[ value ] ).yellow().bright()),
+                GridPiece::Cell { value, given: false, shaded: true } => print!(" {} ", style( NUM_TO_TEXT[ value ] ).cyan()),
+                GridPiece::Cell { value, given: true, shaded: true } => print!(" {} ", style( NUM_TO_TEXT[ value ] ).cyan().bright()),
+                GridPiece::Candidate { value: 0, .. } => print!(" "),
+                GridPiece::Candidate { value, eliminated: false } => print!("{}", style( NUM_TO_TEXT[ value ] ).white()),
+                GridPiece::Candidate { value, eliminated: true } => print!("{}", style( NUM_TO_TEXT[ value ] ).red().bright()),
+                GridPiece::EndOfLine => println!(),
+            }
+        }
+        println!();
+        if rewind {
+            term.move_cursor_up( lines+2 ).ok();
+            term.show_cursor().ok();
+        }
+    }
+
+    // grades the loaded puzzle by how many cells are left for the recursive
+    // solver after the hidden singles in solve_lonerangers run out, counted as
+    // a share of a single grid on a board of several
+    fn grade( &mut self ) -> Difficulty {
+        let puzzle = self.puzzle.clone();
+        self.initialize_with_array( &puzzle );
+        self.solve_lonerangers();
+        let remaining = self.solution.iter().filter( |&&value| value == 0 ).count() * GRID_SIZE / self.board().size;
+        self.initialize_with_array( &puzzle );
+        match remaining {
+            0 => Difficulty::Easy,
+            1..=GRADE_MEDIUM => Difficulty::Medium,
+            _ if remaining <= GRADE_HARD => Difficulty::Hard,
+            _ => Difficulty::Expert,
+        }
+    }
+
+    fn solve_fast( &mut self, limit: usize) {
+        self.solution_count = 0;
+        self.limit = limit;
+        self.depth = 0;
+        let show_candidates = self.app_options.debug && self.app_options.candidates;
+        let before = if show_candidates { self.do_markup(); Some( self.candidates() ) } else { None };
+        self.solve_lonerangers();
+        if let Some( trace ) = self.trace.as_mut() { trace.start( &self.solution ); }
+        if show_candidates {
+            self.display_candidates( "Candidates after hidden singles, eliminated in red:".to_string(), &self.candidates(), before.as_deref() );
+        }
+        self.solve_recursive_fast();
+    }
+
+    // keeps the solution found and checks the puzzle has no other, without
+    // pausing the debugger for the second search
+    fn has_unique_solution( &mut self ) -> bool {
+        let solution = self.solution.clone();
+        let puzzle = self.puzzle.clone();
+        let debug_run = self.debug_run.take();
+        self.initialize_with_array( &puzzle );
+        self.solve_fast( 2 );
+        let unique = self.solution_count == 1;
+        self.debug_run = debug_run;
+        self.solution = solution;
+        self.solution_count = 1;
+        unique
+    }
+
+    fn solve_random( &mut self, limit: usize) {
+        self.solution_count = 0;
+        self.limit = limit;
+        self.nodes = 0;
+        self.solve_recursive_random();
+    }
+
+    // solves easy cells: a value that fits in only one cell of a unit goes there
+    #[allow(clippy::needless_range_loop)]
+    fn solve_lonerangers( &mut self ) {
+        self.do_markup();
+        loop {
+            self.prune_links();
+            let mut solved = 0;
+            for value in 1..GRID_SQRT+1 {
+                let bitmap = NUM_TO_BITMAP[ value ];
+                for u in 0..self.constraints.units.len() {
+                    let mut count = 0;
+                    let mut pos = 0;
+                    for &p in self.constraints.units[ u ].iter() {
+                        if self.solution[ p ] == 0 && (( self.markup[ p ] & bitmap ) == 0) {
+                            count+= 1;
+                            if count > 1 { break; }
+                            pos = p;
+                        }
+                    }
+                    if count == 1 {
+                        self.set_value_and_markup(pos, value);
+                        solved += 1;
+                    }
+                }
+            }
+            if solved == 0 {
+                break;
+            }
+        }
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    fn solve_recursive_fast( &mut self ) { 
+        if self.deadline.is_some() {
+            self.nodes += 1;
+            if self.node_past_deadline() { return; }
+        }
+        if self.debug_run == Some( DebugRun::Quit ) { return; }
+        if self.app_options.verbose && self.app_options.debug && self.trace.is_none() && self.debug_run.is_none() { 
+            self.display( "....solving......".to_string() );
+        }
+        if let Some( (pos, candidates) ) = self.next_move() {
+            for value in 1..GRID_SQRT+1 {
+                if  ( candidates & NUM_TO_BITMAP[ value ] ) != 0 {
+                    self.solution[ pos ] = value;
+                    if let Some( trace ) = self.trace.as_mut() { trace.tried( pos, value ); }
+                    self.depth += 1;
+                    if self.debug_run.is_some() { self.debug_pause( DebugEvent::Try { pos, value, candidates } ); }
+                    self.solve_recursive_fast();  // recurse!
+                    if self.solution_count == self.limit || self.debug_run == Some( DebugRun::Quit ) { return; }
+                    self.solution[ pos ] = 0;
+                    if let Some( trace ) = self.trace.as_mut() { trace.backtracked( pos, value ); }
+                    if self.debug_run.is_some() { self.debug_pause( DebugEvent::Backtrack { pos, value, candidates } ); }
+                    self.depth -= 1;
+                }
+            }
+            return;
+        }
+        if !killer::cages_satisfied( &self.cages, &self.solution ) { return; }
+        if !relations::links_satisfied( &self.links, &self.solution ) { return; }
+        if !clues::clues_satisfied( &self.clues, &self.solution ) { return; }
+        if let Some( trace ) = self.trace.as_mut() { trace.solved(); }
+        if self.debug_run.is_some() { self.debug_pause( DebugEvent::Solution ); }
+        self.solution_count += 1;  // only reaches this point recursively when all cells are solved
+    }
+    
+    // waits for a key when the debugger's run stops at `event`: a decision between
+    // candidates when stepping, every try and backtrack too when verbose, the
+    // next backtrack or the next solution
+    fn debug_pause( &mut self, event: DebugEvent ) {
+        let run = match self.debug_run { Some( DebugRun::Quit ) | None => return, Some( run ) => run };
+        let stop = match ( run, event ) {
+            ( _, DebugEvent::Solution ) => true,
+            ( DebugRun::Step, DebugEvent::Try { candidates, .. } ) => self.app_options.verbose || candidates.count_ones() > 1,
+            ( DebugRun::Step, DebugEvent::Backtrack { .. } ) | ( DebugRun::ToBacktrack, DebugEvent::Backtrack { .. } ) => true,
+            _ => false,
+        };
+        if !stop { return; }
+        let term = Term::stdout();
+        loop {
+            let ( heading, detail ) = match event {
+                DebugEvent::Try { pos, value, candidates } => ( format!( "Trying {} = {}", self.board().cell_name( pos ), value ), candidates ),
+                DebugEvent::Backtrack { pos, value, candidates } => ( format!( "Backtracking, {} = {} failed", self.board().cell_name( pos ), value ), candidates ),
+                DebugEvent::Solution => ( "Solution found".to_string(), 0 ),
+            };
+            term.clear_screen().ok();
+            Sudoku::display_pieces( heading, self.grid_pieces_for( &self.puzzle, &self.solution ), false );
+            let digits: Vec<String> = ( 1..GRID_SQRT+1 ).filter( |&v| detail & NUM_TO_BITMAP[ v ] != 0 ).map( |v| v.to_string() ).collect();
+            println!( " {} {}  {} {}", style( "depth" ).white(), style( self.depth ).green(), style( "candidates" ).white(), style( digits.join( " " ) ).cyan() );
+            println!( " {}", style( format!( "space/s step  b next backtrack  n next solution  v verbose ({})  c run to the end  q quit",
+                if self.app_options.verbose { "on" } else { "off" } ) ).white() );
+            // without a terminal to read from there is no one to step for
+            let key = if term.is_term() { term.read_key() } else { Err( io::Error::new( io::ErrorKind::NotConnected, "No terminal to read keys from." ) ) };
+            if let Some( run ) = self.debug_key( key ) {
+                self.debug_run = run;
+                return;
+            }
+        }
+    }
+
+    // what a key does at a pause: the run to go on with, None to run to the end
+    // without pausing, or nothing when the debugger waits for another key
+    fn debug_key( &mut self, key: io::Result<Key> ) -> Option<Option<DebugRun>> {
+        match key {
+            Ok( Key::Char(' ') ) | Ok( Key::Char('s') ) | Ok( Key::ArrowRight ) | Ok( Key::Enter ) => Some( Some( DebugRun::Step ) ),
+            Ok( Key::Char('b') ) => Some( Some( DebugRun::ToBacktrack ) ),
+            Ok( Key::Char('n') ) => Some( Some( DebugRun::ToSolution ) ),
+            Ok( Key::Char('v') ) => {
+                self.app_options.verbose = !self.app_options.verbose;
+                None
+            },
+            Ok( Key::Char('c') ) | Err( _ ) => {
+                // the rest of the puzzle runs without pausing or animating
+                self.app_options.verbose = false;
+                Some( None )
+            },
+            Ok( Key::Char('q') ) | Ok( Key::Escape ) => Some( Some( DebugRun::Quit ) ),
+            Ok( _ ) => None,
+        }
+    }
+
+    // whether a search with a time budget has run out of it at its latest node,
+    // looking at the clock only every DEADLINE_CHECK_NODES nodes
+    fn node_past_deadline( &mut self ) -> bool {
+        if self.nodes.is_multiple_of( DEADLINE_CHECK_NODES ) && self.deadline.is_some_and( |deadline| Instant::now() > deadline ) { self.timed_out = true; }
+        self.timed_out
+    }
+
+    // whether a search with a time budget has run out of it
+    fn past_deadline( &mut self ) -> bool {
+        if self.deadline.is_some_and( |deadline| Instant::now() > deadline ) { self.timed_out = true; }
+        self.timed_out
+    }
+
+    // the cell to branch on next and its candidates: the first empty cell for a
+    // standard grid; variant constraints and clues make a poor first choice
+    // expensive, so there the cell with fewest candidates is used
+    fn next_move( &self ) -> Option<(usize, usize)> {
+        if self.app_options.variant == Variant::Standard && self.clues.is_empty() {
+            let pos = ( 0..GRID_SIZE ).find( |&pos| self.solution[ pos ] == 0 )?;
+            return Some( ( pos, !self.invalid_values_as_bits( pos ) & ALL_DIGITS ) );
+        }
+        let mut candidates = vec![ 0; self.board().size ];
+        let mut best = None;
+        let mut best_count = usize::MAX;
+        for (pos, candidate) in candidates.iter_mut().enumerate() {
+            if self.solution[ pos ] == 0 {
+                *candidate = !self.invalid_values_as_bits(pos) & ALL_DIGITS;
+                let count = candidate.count_ones() as usize;
+                if count < best_count {
+                    best = Some( pos );
+                    best_count = count;
+                    if count <= 1 { return Some( ( pos, *candidate ) ); }
+                }
+            }
+        }
+        let pos = best?;
+        // a digit with one place left in a unit is the only branch worth taking,
+        // and a digit with none is a dead end
+        for unit in self.constraints.units.iter() {
+            let mut seen = 0;
+            let mut twice = 0;
+            for &p in unit.iter() {
+                let bits = if self.solution[ p ] != 0 { NUM_TO_BITMAP[ self.solution[ p ] ] } else { candidates[ p ] };
+                twice |= seen & bits;
+                seen |= bits;
+            }
+            if seen != ALL_DIGITS { return Some( ( pos, 0 ) ); }
+            let once = seen & !twice;
+            if let Some( &p ) = unit.iter().find( |&&p| self.solution[ p ] == 0 && candidates[ p ] & once != 0 ) {
+                return Some( ( p, candidates[ p ] & once ) );
+            }
+        }
+        Some( ( pos, candidates[ pos ] ) )
+    }
+
+    fn random_node_limit( &self ) -> usize {
+        match self.app_options.variant {
+            Variant::Jigsaw | Variant::NonConsecutive => RANDOM_NODE_LIMIT,
+            _ => usize::MAX,
+        }
+    }
+
+    fn solve_recursive_random( &mut self ) { 
+        self.nodes += 1;
+        if self.nodes > self.random_node_limit() || self.node_past_deadline() { return; }
+        let mut numbers: [usize; GRID_SQRT] = [0; GRID_SQRT];
+        for (pos, number) in numbers.iter_mut().enumerate() { *number = pos+1 }
+        if let Some( (pos, candidates) ) = self.next_move() {
+            Sudoku::shuffle(&mut numbers);
+            for value in 0..GRID_SQRT {
+                if  ( candidates & NUM_TO_BITMAP[ numbers[ value ] ] ) != 0 {
+                    self.solution[ pos ] = numbers[ value ];
+                    self.solve_recursive_random();  // recurse!
+                    if self.solution_count == self.limit { return; }
+                    self.solution[ pos ] = 0;
+                }
+            }
+            return;
+        }
+        if !killer::cages_satisfied( &self.cages, &self.solution ) { return; }
+        if !relations::links_satisfied( &self.links, &self.solution ) { return; }
+        if !clues::clues_satisfied( &self.clues, &self.solution ) { return; }
+        self.solution_count += 1;  // only reaches this point recursively when all cells are solved
+    }
+
+    fn invalid_values_as_bits( &self, pos: usize ) -> usize {
+        let mut bits: usize = !self.allowed[ pos ] & ALL_DIGITS;  // digits the cell's mask rules out
+        for &p in self.constraints.peers[ pos ].iter() {
+            bits |= NUM_TO_BITMAP[ self.solution[ p ] ];  // check every unit and cage the cell is in
+        }
+        if self.cage_of[ pos ] != killer::NO_CAGE {
+            bits |= self.cage_invalid_bits( self.cage_of[ pos ] ); // check cage sum
+        }
+        for &l in self.links_of[ pos ].iter() {
+            let link = self.links[ l ];
+            let other = link.other( pos );
+            if self.solution[ other ] != 0 {
+                bits |= !link.allowed( pos, NUM_TO_BITMAP[ self.solution[ other ] ] ) & ALL_DIGITS; // check dots and signs
+            }
+        }
+        for &c in self.clues_of[ pos ].iter() {
+            bits |= self.clue_invalid_bits( c, pos ); // check thermometers, arrows and sandwiches
+        }
+        bits
+    }
+
+    // generates puzzles until one grades at the difficulty asked for, keeping
+    // the last one after GENERATE_ATTEMPTS or when the time budget runs out;
+    // returns false when the budget ran out before any puzzle was made
+    fn generate( &mut self ) -> bool {
+        let mut last: Option<(String, String)> = None;
+        for _ in 0..GENERATE_ATTEMPTS {
+            if !self.generate_once() { break; }
+            let wanted = self.app_options.difficulty;
+            if wanted.is_none() || self.past_deadline() || Some( self.grade() ) == wanted { return true; }
+            last = self.puzzle_line().ok().map( |line| ( line, self.side_line() ) );
+        }
+        // the attempt cut short by the budget is dropped for the one before it
+        let restored = last.is_some_and( |( line, side )| {
+            let ( deadline, timed_out ) = ( self.deadline.take(), self.timed_out );
+            let loaded = self.initialize_with_line( &line, self.side_filename().map( |_| side.as_str() ) ).is_ok();
+            self.deadline = deadline;
+            self.timed_out = timed_out;
+            loaded
+        });
+        restored || !self.past_deadline()
+    }
+
+    // generates a puzzle, or returns false when the time budget runs out before
+    // there is a solution to take givens from; once it runs out while givens are
+    // being taken the rest are left in place
+    fn generate_once( &mut self ) -> bool {
+
+        // generate a random solution, some jigsaw layouts have none so those are given up on
+        self.set_cages( Vec::new() );
+        if self.side_filename().is_some() {
+            self.set_links( Vec::new() );
+        }
+        self.allowed = vec![ ALL_DIGITS; self.board().size ];
+        loop {
+            if self.past_deadline() { return false; }
+            if self.app_options.variant == Variant::Jigsaw {
+                self.set_regions( jigsaw::random_regions() );
+            }
+            self.clear();
+            self.solve_random( 1 );
+            if self.solution_count != 1 { continue; }
+
+            // lay cages over the solution, they can replace most givens; a layout
+            // with more cages than labels is given up on along with its solution
+            if self.app_options.variant == Variant::Killer {
+                match killer::random_cages( &self.solution ) {
+                    Ok( cages ) => self.set_cages( cages ),
+                    Err( _ ) => continue,
+                }
+            }
+            break;
+        }
+
+        // every qualifying pair of neighbours gets a dot
+        if self.app_options.variant == Variant::Kropki {
+            self.set_links( relations::all_dots( &self.solution ) );
+        }
+
+        // every pair of neighbours gets a sign or every cell a parity mark, these are
+        // removed along with the givens so a puzzle trades some givens for them
+        let signs = if self.app_options.variant == Variant::GreaterThan { relations::all_signs( &self.solution ) } else { Vec::new() };
+        let marks = if self.app_options.variant == Variant::EvenOdd { parity::parity_masks( &self.solution ) } else { Vec::new() };
+        let mut keep = vec![ true; signs.len() + marks.len() ];
+        self.keep_extra_clues( &signs, &marks, &keep );
+
+        // copy solution 
+        let size = self.board().size;
+        let mut new_puzzle = self.solution.clone();
+    
+        // list to randomly remove numbers from solved board, followed by the signs or marks
+        let mut removelist: Vec<usize> = ( 0..size + keep.len() ).collect();
+        Sudoku::shuffle(&mut removelist);
+    
+        // randomly remove a number, along with its partner when the givens are
+        // symmetric, and confirm there is only one solution all the way or reverse it
+        let mut tried = vec![ false; size ];
+        for (i, &item) in removelist.iter().enumerate() { 
+            if item >= size {
+                let e = item - size;
+                keep[e] = false;
+                self.keep_extra_clues( &signs, &marks, &keep );
+                self.initialize_with_array( &new_puzzle );
+                self.solve_fast( 2 );
+                if self.solution_count != 1 || self.timed_out {
+                    keep[e] = true;
+                }
+                continue;
+            }
+            let pos = item;
+            let partner = self.app_options.symmetry.partner( self.board(), pos );
+            tried[ pos ] = true;
+            if partner != pos && tried[ partner ] { continue; }
+            let save_items = ( new_puzzle[ pos ], new_puzzle[ partner ] );
+            new_puzzle[ pos ] = 0;
+            new_puzzle[ partner ] = 0;
+            self.initialize_with_array( &new_puzzle );
+            if self.app_options.debug { 
+                self.display( format!("Removing {} : {}   ", i, item) );
+            }
+            self.solve_fast( 2 );
+            if self.solution_count != 1 || self.timed_out {
+                new_puzzle[ pos ] = save_items.0;
+                new_puzzle[ partner ] = save_items.1;
+            }
+        }
+        self.keep_extra_clues( &signs, &marks, &keep );
+        // transfer values from the new puzzle
+        if self.app_options.debug { 
+            self.solution_count = 1;
+            self.limit = 1;
+            self.display( "With solution...              ".to_string() );
+        }
+        self.initialize_with_array( &new_puzzle );
+        if self.app_options.debug { 
+            self.solution_count = 1
+        }
+        true
+    }
+
+    // puts back the signs or parity marks the generator is keeping
+    fn keep_extra_clues( &mut self, signs: &[Link], marks: &[usize], keep: &[bool] ) {
+        if !signs.is_empty() {
+            self.set_links( signs.iter().zip( keep.iter() ).filter( |&(_, &kept)| kept ).map( |(&sign, _)| sign ).collect() );
+        }
+        if !marks.is_empty() {
+            for pos in 0..marks.len() {
+                self.allowed[ pos ] = if keep[ pos ] { marks[ pos ] } else { ALL_DIGITS };
+            }
+        }
+    }
+
+    fn shuffle<T>(v: &mut [T]) {
+        let mut rng = rand::thread_rng();
+        let len = v.len();
+         for n in 0..len {
+            let i = rng.gen_range(0, len - n);
+            v.swap(i, len - n - 1);
+        }
+    }
+}
+
+// a solved grid shared by the unit tests of every module
+#[cfg(test)]
+mod tests {
+    use super::{AppOptions, DebugRun, Sudoku, Variant, GRID_SIZE};
+    use crate::output::tests::Scratch;
+    use console::Key;
+    use std::time::{Duration, Instant};
+    use std::{fs, io};
+
+    pub const SOLUTION: &str = "647529138285413769193867452758134296321698574469752381816275943534981627972346815";
+
+    pub fn grid( line: &str ) -> [usize; GRID_SIZE] {
+        let mut grid = [0; GRID_SIZE];
+        for (pos, c) in line.chars().enumerate() {
+            grid[ pos ] = c.to_digit( 10 ).unwrap_or( 0 ) as usize;
+        }
+        grid
+    }
+
+    // the solution with its first row left to fill in, loaded and solved
+    fn row_to_fill() -> Sudoku {
+        let mut puzzle = grid( SOLUTION );
+        for value in puzzle[ ..9 ].iter_mut() { *value = 0; }
+        let mut sudoku = Sudoku::new( AppOptions::default() );
+        sudoku.initialize_with_array( &puzzle );
+        sudoku.solve_fast( 1 );
+        sudoku
+    }
+
+    #[test]
+    fn checking_a_grid_names_the_wrong_and_consistent_cells() {
+        let mut sudoku = row_to_fill();
+        // r1c1 agrees with the solution, r1c2 contradicts it
+        let mut played = grid( SOLUTION );
+        played[1] = 8;
+        played[0] = 6;
+        for value in played[ 2..9 ].iter_mut() { *value = 0; }
+        let check = sudoku.check_grid( &played ).unwrap();
+        assert_eq!( check.consistent, vec![ 0 ] );
+        assert_eq!( check.wrong, vec![ 1 ] );
+        // the 8 meets the given 8 at r2c2, so the grid cannot be solved any more
+        assert!( !check.solvable );
+        assert_eq!( sudoku.solution, grid( SOLUTION ).to_vec() );
+    }
+
+    #[test]
+    fn checking_a_grid_with_right_cells_leaves_it_solvable() {
+        let mut sudoku = row_to_fill();
+        let mut played = [0; GRID_SIZE];
+        played[0] = 6;
+        played[4] = 2;
+        let check = sudoku.check_grid( &played ).unwrap();
+        assert_eq!( check.consistent, vec![ 0, 4 ] );
+        assert!( check.wrong.is_empty() );
+        assert!( check.solvable );
+        assert_eq!( sudoku.check_grid( &[ 9; GRID_SIZE ] ).unwrap_err(), "The grid changes the given 2 at r2c1." );
+    }
+
+    #[test]
+    fn the_footer_leaves_out_counts_a_run_did_not_make() {
+        assert_eq!( super::footer( 0.5, 2 ), "Elapsed time: 0.500 seconds. Puzzles completed: 2. Peformance: 4.000 puzzles/second." );
+        assert_eq!( super::footer( 0.0, 1 ), "Elapsed time: 0.000 seconds. Puzzles completed: 1." );
+        assert_eq!( super::footer( 0.0, 0 ), "Elapsed time: 0.000 seconds." );
+        assert_eq!( super::footer( 1.0, -1 ), "Elapsed time: 1.000 seconds." );
+    }
+
+    #[test]
+    fn a_constraint_file_is_rejected_on_a_samurai_board() {
+        let options = AppOptions { variant: Variant::Samurai, constraints_filename: Some( "clues.json".to_string() ), ..AppOptions::default() };
+        let error = Sudoku::new( options ).read_clue_specs().unwrap_err();
+        assert_eq!( error.to_string(), "A constraint file is only for a 9x9 board, not a samurai one." );
+    }
+
+    #[test]
+    fn a_failed_key_read_stops_stepping() {
+        let mut sudoku = Sudoku::new( AppOptions { debug: true, verbose: true, ..AppOptions::default() } );
+        assert_eq!( sudoku.debug_key( Err( io::Error::new( io::ErrorKind::NotConnected, "no terminal" ) ) ), Some( None ) );
+        assert!( !sudoku.app_options.verbose );
+        assert_eq!( sudoku.debug_key( Ok( Key::Unknown ) ), None );
+        assert_eq!( sudoku.debug_key( Ok( Key::Char('v') ) ), None );
+        assert!( sudoku.app_options.verbose );
+        assert_eq!( sudoku.debug_key( Ok( Key::Char('b') ) ), Some( Some( DebugRun::ToBacktrack ) ) );
+        assert_eq!( sudoku.debug_key( Ok( Key::Char('q') ) ), Some( Some( DebugRun::Quit ) ) );
+    }
+
+    #[test]
+    fn a_puzzle_without_a_terminal_runs_to_the_end_instead_of_stepping() {
+        // the tests run with their output captured, so no key can be read
+        if console::Term::stdout().is_term() { return; }
+        let mut sudoku = row_to_fill();
+        sudoku.initialize_with_array( &sudoku.puzzle.clone() );
+        sudoku.debug_run = Some( DebugRun::Step );
+        sudoku.solve_fast( 1 );
+        assert_eq!( sudoku.debug_run, None );
+        assert_eq!( sudoku.solution, grid( SOLUTION ).to_vec() );
+    }
+
+    #[test]
+    fn generating_stops_retrying_once_the_deadline_has_passed() {
+        let mut sudoku = Sudoku::new( AppOptions { variant: Variant::Jigsaw, ..AppOptions::default() } );
+        sudoku.deadline = Some( Instant::now() - Duration::from_secs( 1 ) );
+        assert!( !sudoku.generate_once() );
+        assert!( !sudoku.generate() );
+    }
+
+    #[test]
+    fn a_grid_solves_the_board_when_every_constraint_holds() {
+        let sudoku = Sudoku::new( AppOptions::default() );
+        let mut values = grid( SOLUTION ).to_vec();
+        assert!( sudoku.solves( &values ) );
+        // swapping two cells of a row keeps the row but breaks their columns
+        values.swap( 0, 1 );
+        assert!( !sudoku.solves( &values ) );
+        values.swap( 0, 1 );
+        values[40] = 0;
+        assert!( !sudoku.solves( &values ) );
+    }
+
+    // solves a puzzle of a variant, checking it has exactly the one solution
+    fn solve_variant( variant: Variant, puzzle: &str, side: Option<&str> ) -> Vec<usize> {
+        let mut sudoku = Sudoku::new( AppOptions { variant, ..AppOptions::default() } );
+        sudoku.initialize_with_line( puzzle, side ).unwrap();
+        sudoku.solve_fast( 1 );
+        assert_eq!( sudoku.solution_count, 1 );
+        assert!( sudoku.has_unique_solution() );
+        assert!( sudoku.solves( &sudoku.solution ) );
+        sudoku.solution.clone()
+    }
+
+    // whether the cells hold every digit once
+    fn every_digit( solution: &[usize], cells: impl Iterator<Item = usize> ) -> bool {
+        let mut digits: Vec<usize> = cells.map( |pos| solution[pos] ).collect();
+        digits.sort_unstable();
+        digits == ( 1..10 ).collect::<Vec<usize>>()
+    }
+
+    #[test]
+    fn an_x_puzzle_solves_with_every_digit_on_both_diagonals() {
+        let solution = solve_variant( Variant::X, "..5.8....1..6....3..................9....2..6....59..7...8...2.4..........6......", None );
+        assert_eq!( solution, grid( "365187249149625873872934615534768192987312456621459387713896524458273961296541738" ).to_vec() );
+        assert!( every_digit( &solution, ( 0..9 ).map( |i| i * 10 ) ) );
+        assert!( every_digit( &solution, ( 0..9 ).map( |i| i * 8 + 8 ) ) );
+    }
+
+    #[test]
+    fn a_hyper_puzzle_solves_with_every_digit_in_each_window() {
+        let solution = solve_variant( Variant::Hyper, "..6.........9.547..1.....93..............4....5..3.6......5..1.1.7....8.........2", None );
+        assert_eq!( solution, grid( "946273851283915476715486293462798135371564928859132647624857319137629584598341762" ).to_vec() );
+        for &( top, left ) in [ (1, 1), (1, 5), (5, 1), (5, 5) ].iter() {
+            assert!( every_digit( &solution, ( 0..9 ).map( |i| ( top + i / 3 ) * 9 + left + i % 3 ) ) );
+        }
+    }
+
+    // whether no two cells a move apart on the board hold the same digit
+    fn apart_by_moves( solution: &[usize], moves: &[(i32, i32)] ) -> bool {
+        ( 0..GRID_SIZE ).all( |pos| moves.iter().all( |&( dy, dx )| {
+            let ( y, x ) = ( ( pos / 9 ) as i32 + dy, ( pos % 9 ) as i32 + dx );
+            !( 0..9 ).contains( &y ) || !( 0..9 ).contains( &x ) || solution[ ( y * 9 + x ) as usize ] != solution[pos]
+        }))
+    }
+
+    #[test]
+    fn anti_knight_and_anti_king_puzzles_solve_apart_by_their_moves() {
+        let knight = [ (1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2) ];
+        let king = [ (1, 1), (1, -1), (-1, -1), (-1, 1) ];
+        let solution = solve_variant( Variant::AntiKnight, ".....39.....2........1.........4............43...........8.....6...7..........1.5", None );
+        assert_eq!( solution, grid( "578463912961257843243198657827349561196725384354681729735814296619572438482936175" ).to_vec() );
+        assert!( apart_by_moves( &solution, &knight ) );
+        let solution = solve_variant( Variant::AntiKing, ".7.24.6..5...8....9..............8..26.............7.5.......27.....14.....9....6", None );
+        assert_eq!( solution, grid( "873249651526187349914536278357614892269875134481392765135468927692751483748923516" ).to_vec() );
+        assert!( apart_by_moves( &solution, &king ) );
+        // a standard solution has knight and king moves to the same digit
+        assert!( !apart_by_moves( &grid( SOLUTION ), &knight ) );
+        assert!( !apart_by_moves( &grid( SOLUTION ), &king ) );
+    }
+
+    const KROPKI_PUZZLE: &str = ".................................9...............................................";
+    const KROPKI_DOTS: &str = "w:r1c6-r2c6 w:r1c7-r2c7 b:r1c7-r1c8 w:r1c8-r1c9 b:r1c9-r2c9 b:r2c1-r2c2 w:r2c2-r3c2 w:r2c5-r3c5 w:r2c8-r2c9 w:r3c3-r4c3 w:r3c3-r3c4 b:r3c5-r4c5 b:r3c5-r3c6 w:r3c6-r4c6 w:r3c7-r4c7 w:r3c7-r3c8 w:r3c8-r4c8 w:r4c2-r4c3 w:r4c4-r5c4 b:r4c4-r4c5 w:r4c5-r4c6 w:r4c7-r4c8 b:r5c3-r6c3 w:r5c3-r5c4 w:r5c5-r6c5 w:r5c5-r5c6 w:r5c6-r6c6 b:r5c8-r6c8 b:r5c9-r6c9 w:r6c2-r6c3 b:r6c6-r7c6 w:r6c6-r6c7 w:r6c8-r7c8 w:r6c8-r6c9 w:r7c1-r8c1 w:r7c2-r8c2 w:r7c3-r8c3 w:r7c3-r7c4 w:r7c4-r8c4 b:r7c5-r7c6 w:r7c6-r8c6 w:r7c6-r7c7 w:r7c7-r8c7 b:r7c7-r7c8 w:r7c8-r8c8 w:r8c2-r8c3 w:r8c3-r8c4 w:r8c6-r9c6 w:r8c6-r8c7 b:r8c7-r9c7 w:r8c9-r9c9 w:r9c2-r9c3 w:r9c3-r9c4 w:r9c6-r9c7";
+    const KROPKI_SOLUTION: &str = "681759423429138576735642891376421985594387162812596734158963247267814359943275618";
+
+    #[test]
+    fn a_non_consecutive_puzzle_solves_without_neighbours_one_apart() {
+        // a generated puzzle with more of its solution given, so proving it unique is quick
+        let solution = solve_variant( Variant::NonConsecutive, "3...8...9...75..8..41...2..58...3..29...4...5...5...4.4.3...82..9...8...7...6...1", None );
+        assert_eq!( solution, grid( "375284169629751483841396257584613792962847315137529648413975826296138574758462931" ).to_vec() );
+        let one_apart = |a: usize, b: usize| solution[a] + 1 == solution[b] || solution[b] + 1 == solution[a];
+        assert!( ( 0..GRID_SIZE ).all( |pos| ( pos % 9 == 8 || !one_apart( pos, pos + 1 ) ) && ( pos >= 72 || !one_apart( pos, pos + 9 ) ) ) );
+    }
+
+    #[test]
+    fn a_kropki_puzzle_solves_with_every_dot_holding() {
+        let solution = solve_variant( Variant::Kropki, KROPKI_PUZZLE, Some( KROPKI_DOTS ) );
+        assert_eq!( solution, grid( KROPKI_SOLUTION ).to_vec() );
+        let cell = |name: &str| {
+            let ( row, col ) = name[1..].split_once( 'c' ).unwrap();
+            ( row.parse::<usize>().unwrap() - 1 ) * 9 + col.parse::<usize>().unwrap() - 1
+        };
+        for dot in KROPKI_DOTS.split_whitespace() {
+            let ( colour, cells ) = dot.split_once( ':' ).unwrap();
+            let ( a, b ) = cells.split_once( '-' ).unwrap();
+            let ( low, high ) = ( solution[ cell( a ) ].min( solution[ cell( b ) ] ), solution[ cell( a ) ].max( solution[ cell( b ) ] ) );
+            assert_eq!( if colour == "w" { low + 1 } else { low * 2 }, high, "{}", dot );
+        }
+    }
+
+    #[test]
+    fn resuming_generation_trims_the_side_file_and_solves_the_puzzles_left() {
+        let puzzles = Scratch::new( "resume-puzzles" );
+        let dots = Scratch::new( "resume-dots" );
+        let solutions = Scratch::new( "resume-solutions" );
+        // the run stopped after the dots of a second puzzle and before its givens
+        fs::write( &puzzles.0, format!( "{}\n", KROPKI_PUZZLE ) ).unwrap();
+        fs::write( &dots.0, format!( "{}\nw:r1c1-r1c2\n", KROPKI_DOTS ) ).unwrap();
+        fs::write( &solutions.0, "" ).unwrap();
+        let app_options = AppOptions {
+            filename: puzzles.0.clone(),
+            dots_filename: dots.0.clone(),
+            solutions_filename: solutions.0.clone(),
+            output_solutions: true,
+            generate: true,
+            append: true,
+            resume: true,
+            number: 1,
+            variant: Variant::Kropki,
+            ..AppOptions::default()
+        };
+        let mut sudoku = Sudoku::new( app_options );
+        assert_eq!( sudoku.generate_puzzles_to_file().unwrap(), 0 );
+        assert_eq!( puzzles.contents(), format!( "{}\n", KROPKI_PUZZLE ) );
+        assert_eq!( dots.contents(), format!( "{}\n", KROPKI_DOTS ) );
+        assert_eq!( solutions.contents(), format!( "{}\n", KROPKI_SOLUTION ) );
+    }
+}
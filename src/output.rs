@@ -0,0 +1,147 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+
+fn temp_filename( filename: &str ) -> String {
+    format!( "{}.tmp", filename )
+}
+
+// whether a file has content whose last byte is not a newline; only a missing
+// file counts as empty, any other error is passed on so the file is left alone
+fn ends_without_newline( filename: &str ) -> io::Result<bool> {
+    let mut file = match File::open( filename ) {
+        Ok( file ) => file,
+        Err( e ) if e.kind() == io::ErrorKind::NotFound => return Ok( false ),
+        Err( e ) => return Err( e ),
+    };
+    if file.metadata()?.len() == 0 { return Ok( false ); }
+    file.seek( SeekFrom::End( -1 ) )?;
+    let mut last = [0; 1];
+    file.read_exact( &mut last )?;
+    Ok( last[0] != b'\n' )
+}
+
+// writes a whole file to a temp file next to it and renames that over the top,
+// so an interrupted write leaves the old file as it was
+pub fn write_atomically( filename: &str, contents: &[u8] ) -> io::Result<()> {
+    let temp = temp_filename( filename );
+    let mut file = File::create( &temp )?;
+    file.write_all( contents )?;
+    file.sync_all()?;
+    fs::rename( &temp, filename )
+}
+
+// An output file of one record per line, flushed line by line. Appending
+// writes to the end of the file in place, which is what --resume picks up
+// from. Overwriting writes to <file>.tmp and `finish` renames it over the
+// file, so a run that stops early leaves the old file untouched.
+pub struct LinesWriter {
+    writer: BufWriter<File>,
+    filename: String,
+    temp: Option<String>,
+}
+
+impl LinesWriter {
+
+    pub fn open( filename: &str, append: bool ) -> io::Result<LinesWriter> {
+        if append {
+            let needs_newline = ends_without_newline( filename )?;
+            let file = OpenOptions::new().create(true).append(true).open( filename )?;
+            let mut writer = BufWriter::new( file );
+            if needs_newline { writer.write_all( b"\n" )?; }
+            Ok( LinesWriter { writer, filename: filename.to_string(), temp: None } )
+        } else {
+            let temp = temp_filename( filename );
+            let writer = BufWriter::new( File::create( &temp )? );
+            Ok( LinesWriter { writer, filename: filename.to_string(), temp: Some( temp ) } )
+        }
+    }
+
+    pub fn write_line( &mut self, line: &str ) -> io::Result<()> {
+        self.writer.write_all( line.as_bytes() )?;
+        self.writer.write_all( b"\n" )?;
+        self.writer.flush()
+    }
+
+    // syncs the file and, when overwriting, puts it in place of the old one
+    pub fn finish( mut self ) -> io::Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        match self.temp.take() {
+            Some( temp ) => fs::rename( temp, &self.filename ),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::path::Path;
+
+    // a file of its own in the temp directory for each test, removed with its
+    // temp file when the test is done
+    pub struct Scratch( pub String );
+
+    impl Scratch {
+        pub fn new( name: &str ) -> Scratch {
+            let path = std::env::temp_dir().join( format!( "sudoku-output-{}-{}", std::process::id(), name ) );
+            let scratch = Scratch( path.to_string_lossy().into_owned() );
+            scratch.remove();
+            scratch
+        }
+
+        fn remove( &self ) {
+            fs::remove_file( &self.0 ).ok();
+            fs::remove_file( temp_filename( &self.0 ) ).ok();
+        }
+
+        pub fn contents( &self ) -> String {
+            fs::read_to_string( &self.0 ).unwrap()
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop( &mut self ) { self.remove(); }
+    }
+
+    #[test]
+    fn overwriting_renames_the_temp_file_over_the_old_one() {
+        let file = Scratch::new( "overwrite" );
+        fs::write( &file.0, "old\n" ).unwrap();
+        let mut writer = LinesWriter::open( &file.0, false ).unwrap();
+        writer.write_line( "one" ).unwrap();
+        writer.write_line( "two" ).unwrap();
+        writer.finish().unwrap();
+        assert_eq!( file.contents(), "one\ntwo\n" );
+        assert!( !Path::new( &temp_filename( &file.0 ) ).exists() );
+
+        write_atomically( &file.0, b"three\n" ).unwrap();
+        assert_eq!( file.contents(), "three\n" );
+        assert!( !Path::new( &temp_filename( &file.0 ) ).exists() );
+    }
+
+    #[test]
+    fn appending_starts_a_new_line_after_a_file_without_one() {
+        let file = Scratch::new( "append" );
+        fs::write( &file.0, "one" ).unwrap();
+        let mut writer = LinesWriter::open( &file.0, true ).unwrap();
+        writer.write_line( "two" ).unwrap();
+        writer.finish().unwrap();
+        assert_eq!( file.contents(), "one\ntwo\n" );
+
+        let mut writer = LinesWriter::open( &file.0, true ).unwrap();
+        writer.write_line( "three" ).unwrap();
+        writer.finish().unwrap();
+        assert_eq!( file.contents(), "one\ntwo\nthree\n" );
+    }
+
+    #[test]
+    fn an_unfinished_overwrite_leaves_the_old_file_untouched() {
+        let file = Scratch::new( "dropped" );
+        fs::write( &file.0, "old\n" ).unwrap();
+        let mut writer = LinesWriter::open( &file.0, false ).unwrap();
+        writer.write_line( "new" ).unwrap();
+        drop( writer );
+        assert_eq!( file.contents(), "old\n" );
+    }
+}
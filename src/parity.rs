@@ -0,0 +1,105 @@
+use crate::{ALL_DIGITS, GRID_SQRT, GRID_SIZE, NUM_TO_BITMAP, NUM_TO_TEXT};
+
+pub const EVEN: usize = 0b_010101010 & ALL_DIGITS;
+pub const ODD: usize = !EVEN & ALL_DIGITS;
+
+// labels for masks other than even and odd, which are always 'e' and 'o'
+const MASK_LABELS: &[u8] = b"abcdfghijklmnpqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// reads a mask map of one label per cell followed by a `label=digits` field for
+// every label that is not built in, e.g. "e.o.a... a=123"; 'e' allows the even
+// digits, 'o' the odd ones and '.' any digit
+pub fn parse_masks( map: &str, definitions: &[&str] ) -> Result<[usize; GRID_SIZE], String> {
+    let map = map.as_bytes();
+    if map.len() != GRID_SIZE {
+        return Err( format!( "The parity map must have {} cells.", GRID_SIZE ) );
+    }
+    let mut labels: Vec<(u8, usize)> = vec![ ( b'.', ALL_DIGITS ), ( b'e', EVEN ), ( b'o', ODD ) ];
+    for field in definitions {
+        let invalid = || format!( "'{}' is not a valid digit mask.", field );
+        let (label, digits) = field.split_once( '=' ).ok_or_else( invalid )?;
+        let label = match label.as_bytes() {
+            [label] if !labels.iter().any( |(l, _)| l == label ) => *label,
+            _ => return Err( invalid() ),
+        };
+        let mut mask = 0;
+        for digit in digits.chars() {
+            match NUM_TO_TEXT[ 1..GRID_SQRT+1 ].iter().position( |&c| c == digit ) {
+                Some( d ) => mask |= NUM_TO_BITMAP[ d+1 ],
+                None => return Err( invalid() ),
+            }
+        }
+        if mask == 0 { return Err( invalid() ); }
+        labels.push( ( label, mask ) );
+    }
+    let mut masks = [ALL_DIGITS; GRID_SIZE];
+    for (pos, label) in map.iter().enumerate() {
+        masks[pos] = match labels.iter().find( |(l, _)| l == label ) {
+            Some( &(_, mask) ) => mask,
+            None => return Err( format!( "Digit mask '{}' is not defined.", *label as char ) ),
+        };
+    }
+    Ok(masks)
+}
+
+// writes masks back as a mask map with its definitions, the way `parse_masks`
+// reads them; fails when there are more kinds of mask than labels
+pub fn format_masks( masks: &[usize] ) -> Result<String, String> {
+    let mut map = String::new();
+    let mut defined: Vec<usize> = Vec::new();
+    for &mask in masks.iter() {
+        map.push( match mask {
+            ALL_DIGITS => '.',
+            EVEN => 'e',
+            ODD => 'o',
+            _ => {
+                let d = defined.iter().position( |&m| m == mask ).unwrap_or_else( || {
+                    defined.push( mask );
+                    defined.len() - 1
+                });
+                match MASK_LABELS.get( d ) {
+                    Some( &label ) => label as char,
+                    None => return Err( format!( "There are more than {} kinds of digit mask to label.", MASK_LABELS.len() ) ),
+                }
+            },
+        });
+    }
+    for (d, &mask) in defined.iter().enumerate() {
+        let digits: String = ( 1..GRID_SQRT+1 ).filter( |&v| mask & NUM_TO_BITMAP[v] != 0 ).map( |v| NUM_TO_TEXT[v] ).collect();
+        map += &format!( " {}={}", MASK_LABELS[d] as char, digits );
+    }
+    Ok(map)
+}
+
+// the parity mark of every cell of a solved grid
+pub fn parity_masks( solution: &[usize] ) -> Vec<usize> {
+    solution.iter().map( |&value| if NUM_TO_BITMAP[ value ] & EVEN != 0 { EVEN } else { ODD } ).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_read_back_as_they_were_written() {
+        let mut masks = [ALL_DIGITS; GRID_SIZE];
+        masks[0] = EVEN;
+        masks[1] = ODD;
+        masks[2] = 0b_000000111;
+        masks[3] = 0b_110000000;
+        masks[4] = 0b_000000111;
+        let line = format_masks( &masks ).unwrap();
+        assert_eq!( line, format!( "eoaba{} a=123 b=89", ".".repeat( GRID_SIZE - 5 ) ) );
+        let ( map, definitions ) = line.split_at( GRID_SIZE );
+        assert_eq!( parse_masks( map, &definitions.split_whitespace().collect::<Vec<&str>>() ).unwrap(), masks );
+    }
+
+    #[test]
+    fn masks_fail_to_write_once_the_labels_run_out() {
+        // every cell a different set of digits, more kinds than there are labels
+        let masks: Vec<usize> = ( 1..GRID_SIZE+1 ).collect();
+        assert!( GRID_SIZE > MASK_LABELS.len() );
+        assert!( format_masks( &masks ).is_err() );
+        assert!( format_masks( &masks[ ..MASK_LABELS.len() ] ).is_ok() );
+    }
+}
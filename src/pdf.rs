@@ -0,0 +1,411 @@
+use crate::clues::Clue;
+use crate::parity::{EVEN, ODD};
+use crate::render::{self, ExportGrid, ExportItem, LinkMark, Markings, Point};
+use crate::units::{Board, Constraints};
+use crate::{ALL_DIGITS, GRID_BLCK, GRID_SQRT, GRID_SIZE, NUM_TO_TEXT};
+use pdf_writer::types::{LineCapStyle, LineJoinStyle};
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+// A4 in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const PAGE_MARGIN: f32 = 50.0;
+const CAPTION_HEIGHT: f32 = 28.0;
+const ANSWERS_PER_PAGE: usize = 6;
+
+const FONT_REGULAR: Name = Name(b"F1");
+const FONT_BOLD: Name = Name(b"F2");
+
+pub const PAGE_LAYOUTS: [usize; 3] = [2, 4, 6];
+
+// hands out object ids in the order they are needed
+struct RefAllocator {
+    next: i32,
+}
+
+impl RefAllocator {
+    fn bump( &mut self ) -> Ref {
+        let id = Ref::new( self.next );
+        self.next += 1;
+        id
+    }
+}
+
+// the base 14 fonts only cover latin-1 in the standard encoding, keep it simple
+fn pdf_text( text: &str ) -> Vec<u8> {
+    text.chars().map( |c| if c.is_ascii() && !c.is_ascii_control() { c as u8 } else { b'?' } ).collect()
+}
+
+// Helvetica averages about half an em per character, close enough for centring headings
+fn show_centered( content: &mut Content, font: Name, size: f32, y: f32, text: &str ) {
+    let width = text.len() as f32 * size * 0.5;
+    show_text( content, font, size, ( PAGE_WIDTH - width ) / 2.0, y, text );
+}
+
+fn show_text( content: &mut Content, font: Name, size: f32, x: f32, y: f32, text: &str ) {
+    content.begin_text();
+    content.set_font( font, size );
+    content.next_line( x, y );
+    content.show( Str( &pdf_text( text ) ) );
+    content.end_text();
+}
+
+// a circle as four bezier curves, left open for the caller to fill or stroke
+fn circle( content: &mut Content, ( cx, cy ): (f32, f32), r: f32 ) {
+    let k = r * 0.5523;
+    content.move_to( cx + r, cy );
+    content.cubic_to( cx + r, cy + k, cx + k, cy + r, cx, cy + r );
+    content.cubic_to( cx - k, cy + r, cx - r, cy + k, cx - r, cy );
+    content.cubic_to( cx - r, cy - k, cx - k, cy - r, cx, cy - r );
+    content.cubic_to( cx + k, cy - r, cx + r, cy - k, cx + r, cy );
+    content.close_path();
+}
+
+fn polyline( content: &mut Content, points: &[(f32, f32)] ) {
+    content.move_to( points[0].0, points[0].1 );
+    for &(px, py) in points[ 1.. ].iter() {
+        content.line_to( px, py );
+    }
+}
+
+// where a board sits on the page: its left edge, its top edge and the size of a cell
+struct Frame {
+    left: f32,
+    top: f32,
+    cell: f32,
+}
+
+impl Frame {
+    // a point on the board, measured in cells from its top left corner, on the page
+    fn at( &self, ( px, py ): Point ) -> (f32, f32) {
+        ( self.left + px * self.cell, self.top - py * self.cell )
+    }
+
+    fn all( &self, points: &[Point] ) -> Vec<(f32, f32)> {
+        points.iter().map( |&point| self.at( point ) ).collect()
+    }
+}
+
+// the shading of extra units, parity marks, thermometers and arrows, which go
+// under the grid lines and digits
+fn draw_underlay( content: &mut Content, board: &Board, shaded: &[bool], markings: &Markings, frame: &Frame ) {
+    let cell = frame.cell;
+    for (pos, &shade) in shaded.iter().enumerate() {
+        let ( row, col ) = board.coords( pos );
+        let ( x, y ) = frame.at( ( col as f32, row as f32 + 1.0 ) );
+        if shade {
+            content.set_fill_gray( 0.87 );
+            content.rect( x, y, cell, cell );
+            content.fill_nonzero();
+        }
+        match markings.masks.get( pos ).copied().unwrap_or( ALL_DIGITS ) {
+            ALL_DIGITS => (),
+            EVEN => {
+                content.set_fill_gray( 0.8 );
+                content.rect( x + cell * 0.15, y + cell * 0.15, cell * 0.7, cell * 0.7 );
+                content.fill_nonzero();
+            },
+            ODD => {
+                content.set_fill_gray( 0.8 );
+                circle( content, ( x + cell / 2.0, y + cell / 2.0 ), cell * 0.37 );
+                content.fill_nonzero();
+            },
+            mask => {
+                content.set_fill_gray( 0.3 );
+                show_text( content, FONT_REGULAR, cell * 0.2, x + cell * 0.06, y + cell * 0.06, &render::mask_digits( mask ) );
+            },
+        }
+    }
+    for clue in markings.clues.iter() {
+        let points = render::clue_path( board, clue );
+        let path = frame.all( &points );
+        content.save_state();
+        match clue {
+            Clue::Thermometer( _ ) => {
+                content.set_fill_gray( 0.8 );
+                circle( content, path[0], cell * render::BULB_RADIUS );
+                content.fill_nonzero();
+                content.set_stroke_gray( 0.8 );
+                content.set_line_width( cell * 0.3 );
+                content.set_line_cap( LineCapStyle::RoundCap );
+                content.set_line_join( LineJoinStyle::RoundJoin );
+                polyline( content, &path );
+                content.stroke();
+            },
+            Clue::Arrow { circle: start, .. } => {
+                let ( row, col ) = board.coords( *start );
+                content.set_stroke_gray( 0.55 );
+                content.set_line_width( 1.0 );
+                circle( content, frame.at( ( col as f32 + 0.5, row as f32 + 0.5 ) ), cell * render::CIRCLE_RADIUS );
+                content.stroke();
+                polyline( content, &path );
+                content.stroke();
+                polyline( content, &frame.all( &render::arrow_head( &points ) ) );
+                content.stroke();
+            },
+            Clue::Sandwich { .. } => (),
+        }
+        content.restore_state();
+    }
+    content.set_fill_gray( 0.0 );
+}
+
+// killer cages with their sums, dots, signs and sandwich sums, which go over
+// the grid lines
+fn draw_overlay( content: &mut Content, board: &Board, markings: &Markings, frame: &Frame ) {
+    let cell = frame.cell;
+    let font_size = cell * 0.22;
+    for cage in markings.cages.iter() {
+        content.save_state();
+        content.set_line_width( 0.6 );
+        content.set_dash_pattern( [ 2.0, 2.0 ], 0.0 );
+        for ( from, to ) in render::cage_outline( board, &cage.cells ) {
+            polyline( content, &frame.all( &[ from, to ] ) );
+        }
+        content.stroke();
+        content.restore_state();
+        let ( x, y ) = frame.at( render::cage_corner( board, &cage.cells ) );
+        let sum = cage.sum.to_string();
+        content.set_fill_gray( 1.0 );
+        content.rect( x + cell * 0.03, y - cell * 0.3, sum.len() as f32 * font_size * 0.556 + cell * 0.06, cell * 0.27 );
+        content.fill_nonzero();
+        content.set_fill_gray( 0.0 );
+        show_text( content, FONT_REGULAR, font_size, x + cell * 0.06, y - cell * 0.25, &sum );
+    }
+    for link in markings.links.iter() {
+        match render::link_mark( board, link ) {
+            Some( LinkMark::Dot { centre, black } ) => {
+                content.set_line_width( 0.8 );
+                content.set_fill_gray( if black { 0.0 } else { 1.0 } );
+                circle( content, frame.at( centre ), cell * 0.12 );
+                content.fill_nonzero_and_stroke();
+            },
+            Some( LinkMark::Chevron( points ) ) => {
+                content.set_line_width( 1.2 );
+                polyline( content, &frame.all( &points ) );
+                content.stroke();
+            },
+            None => (),
+        }
+    }
+    content.set_fill_gray( 0.0 );
+    for clue in markings.clues.iter() {
+        if let Clue::Sandwich { cells, sum } = clue {
+            let ( x, y ) = frame.at( render::sandwich_label( board, cells ) );
+            let text = sum.to_string();
+            let size = cell * 0.35;
+            show_text( content, FONT_REGULAR, size, x - text.len() as f32 * size * 0.556 / 2.0, y - size * 0.35, &text );
+        }
+    }
+}
+
+// draws one grid with its bottom left corner at (x, y), heavy lines around its
+// blocks or the regions of a jigsaw; digits that are not givens are drawn in
+// grey so the answer key still shows the original clues
+fn draw_grid( content: &mut Content, puzzle: &[usize], solution: &[usize], regions: Option<&[usize; GRID_SIZE]>, x: f32, y: f32, size: f32 ) {
+    let cell = size / GRID_SQRT as f32;
+    for n in 1..GRID_SQRT {
+        let block_edge = regions.is_none() && n.is_multiple_of( GRID_BLCK );
+        content.set_line_width( if block_edge { 1.8 } else { 0.5 } );
+        content.set_stroke_gray( if block_edge { 0.0 } else { 0.5 } );
+        let p = n as f32 * cell;
+        content.move_to( x + p, y );
+        content.line_to( x + p, y + size );
+        content.move_to( x, y + p );
+        content.line_to( x + size, y + p );
+        content.stroke();
+    }
+    content.set_stroke_gray( 0.0 );
+    if let Some( regions ) = regions {
+        content.set_line_width( 1.8 );
+        for pos in 0..GRID_SIZE {
+            let ( row, col ) = ( pos / GRID_SQRT, pos % GRID_SQRT );
+            let ( left, top ) = ( x + col as f32 * cell, y + size - row as f32 * cell );
+            if col + 1 < GRID_SQRT && regions[pos] != regions[pos+1] {
+                content.move_to( left + cell, top );
+                content.line_to( left + cell, top - cell );
+            }
+            if row + 1 < GRID_SQRT && regions[pos] != regions[pos+GRID_SQRT] {
+                content.move_to( left, top - cell );
+                content.line_to( left + cell, top - cell );
+            }
+        }
+        content.stroke();
+    }
+    content.set_line_width( 2.5 );
+    content.rect( x, y, size, size );
+    content.stroke();
+
+    // all digits in Helvetica are 0.556 em wide
+    let font_size = cell * 0.6;
+    for pos in 0..GRID_SIZE {
+        if solution[pos] == 0 { continue; }
+        let row = pos / GRID_SQRT;
+        let col = pos % GRID_SQRT;
+        let given = puzzle[pos] != 0;
+        content.set_fill_gray( if given { 0.0 } else { 0.45 } );
+        let tx = x + col as f32 * cell + ( cell - font_size * 0.556 ) / 2.0;
+        let ty = y + size - ( row + 1 ) as f32 * cell + ( cell - font_size * 0.7 ) / 2.0;
+        let text = NUM_TO_TEXT[ solution[pos] ].to_string();
+        show_text( content, if given { FONT_BOLD } else { FONT_REGULAR }, font_size, tx, ty, &text );
+    }
+    content.set_fill_gray( 0.0 );
+}
+
+// draws every grid of a board at its place in the frame with the markings of its variant
+fn draw_board( content: &mut Content, board: &Board, shaded: &[bool], item: &ExportItem, values: &[usize], frame: &Frame ) {
+    let cell = frame.cell;
+    draw_underlay( content, board, shaded, &item.markings, frame );
+    for (g, grid) in board.grids.iter().enumerate() {
+        let ( gx, gy ) = frame.at( ( grid.left as f32, ( grid.top + GRID_SQRT ) as f32 ) );
+        let ( puzzle, values ) = ( board.grid_values( &item.puzzle, g ), board.grid_values( values, g ) );
+        draw_grid( content, &puzzle, &values, item.markings.regions.as_ref(), gx, gy, cell * GRID_SQRT as f32 );
+    }
+    draw_overlay( content, board, &item.markings, frame );
+}
+
+// lays out up to `per_page` grids on a page in two columns (one column for 2 per page)
+fn grid_page( board: &Board, shaded: &[bool], grids: &[ExportGrid], per_page: usize, heading: &str, page_number: usize ) -> Content {
+    let columns = if per_page <= 2 { 1 } else { 2 };
+    let rows = per_page.div_ceil( columns );
+    let top = PAGE_HEIGHT - PAGE_MARGIN - 30.0;
+    let slot_width = ( PAGE_WIDTH - PAGE_MARGIN * 2.0 ) / columns as f32;
+    let slot_height = ( top - PAGE_MARGIN - 20.0 ) / rows as f32;
+    let size = ( slot_width * 0.85 ).min( slot_height - CAPTION_HEIGHT - 12.0 );
+
+    let mut content = Content::new();
+    show_text( &mut content, FONT_BOLD, 16.0, PAGE_MARGIN, PAGE_HEIGHT - PAGE_MARGIN, heading );
+    for (i, (caption, item, values)) in grids.iter().enumerate() {
+        let slot_x = PAGE_MARGIN + ( i % columns ) as f32 * slot_width;
+        let slot_top = top - ( i / columns ) as f32 * slot_height;
+        let x = slot_x + ( slot_width - size ) / 2.0;
+        let y = slot_top - CAPTION_HEIGHT - size;
+        show_text( &mut content, FONT_REGULAR, 12.0, x, slot_top - CAPTION_HEIGHT + 8.0, caption );
+        let frame = Frame { left: x, top: y + size, cell: size / board.side as f32 };
+        draw_board( &mut content, board, shaded, item, values, &frame );
+    }
+    show_centered( &mut content, FONT_REGULAR, 10.0, PAGE_MARGIN / 2.0, &page_number.to_string() );
+    content
+}
+
+fn caption( prefix: &str, item: &ExportItem ) -> String {
+    match item.difficulty {
+        Some( difficulty ) => format!( "{} {} - {}", prefix, item.number, difficulty ),
+        None => format!( "{} {}", prefix, item.number ),
+    }
+}
+
+// a title page, the puzzles `per_page` to a page and an answer key at the back
+pub fn puzzle_book( title: &str, items: &[ExportItem], board: &Board, constraints: &Constraints, per_page: usize ) -> Vec<u8> {
+    let shaded = constraints.shaded_cells();
+    let mut pages: Vec<Content> = Vec::new();
+
+    let mut content = Content::new();
+    show_centered( &mut content, FONT_BOLD, 36.0, PAGE_HEIGHT * 0.62, title );
+    show_centered( &mut content, FONT_REGULAR, 16.0, PAGE_HEIGHT * 0.62 - 40.0, &format!( "{} puzzles with answer key", items.len() ) );
+    show_centered( &mut content, FONT_REGULAR, 10.0, PAGE_MARGIN, "Made with SUDOKU CLI Solver & Generator" );
+    pages.push( content );
+
+    let puzzles: Vec<ExportGrid> = items.iter().map( |item| ( caption( "Puzzle", item ), item, &item.puzzle[..] ) ).collect();
+    for chunk in puzzles.chunks( per_page ) {
+        let page_number = pages.len() + 1;
+        pages.push( grid_page( board, &shaded, chunk, per_page, "Puzzles", page_number ) );
+    }
+
+    let answers: Vec<ExportGrid> = items.iter()
+        .filter_map( |item| item.solution.as_ref().map( |solution| ( caption( "Answer", item ), item, &solution[..] ) ) )
+        .collect();
+    for chunk in answers.chunks( ANSWERS_PER_PAGE ) {
+        let page_number = pages.len() + 1;
+        pages.push( grid_page( board, &shaded, chunk, ANSWERS_PER_PAGE, "Answer Key", page_number ) );
+    }
+
+    let mut ids = RefAllocator { next: 1 };
+    let catalog_id = ids.bump();
+    let page_tree_id = ids.bump();
+    let info_id = ids.bump();
+    let regular_id = ids.bump();
+    let bold_id = ids.bump();
+    let page_ids: Vec<(Ref, Ref)> = pages.iter().map( |_| ( ids.bump(), ids.bump() ) ).collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog( catalog_id ).pages( page_tree_id );
+    pdf.pages( page_tree_id ).kids( page_ids.iter().map( |&(page_id, _)| page_id ) ).count( pages.len() as i32 );
+    pdf.document_info( info_id ).title( TextStr( title ) ).creator( TextStr( "SUDOKU CLI Solver & Generator" ) );
+    pdf.type1_font( regular_id ).base_font( Name(b"Helvetica") );
+    pdf.type1_font( bold_id ).base_font( Name(b"Helvetica-Bold") );
+
+    for (content, &(page_id, content_id)) in pages.into_iter().zip( page_ids.iter() ) {
+        let mut page = pdf.page( page_id );
+        page.media_box( Rect::new( 0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT ) );
+        page.parent( page_tree_id );
+        page.contents( content_id );
+        let mut resources = page.resources();
+        resources.fonts().pair( FONT_REGULAR, regular_id ).pair( FONT_BOLD, bold_id );
+        resources.finish();
+        page.finish();
+        pdf.stream( content_id, &content.finish() );
+    }
+    pdf.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tests::{cages, clues, constraints, item, masks, regions};
+    use crate::{relations, Variant};
+
+    // the content of a puzzle book of one puzzle, whose streams are not compressed
+    fn book( variant: Variant, markings: Markings ) -> String {
+        let book = puzzle_book( "Test", &[ item( markings ) ], Board::of( variant ), &constraints( variant ), 2 );
+        String::from_utf8_lossy( &book ).into_owned()
+    }
+
+    #[test]
+    fn a_killer_book_draws_its_cages_dashed_with_their_sums() {
+        assert!( !book( Variant::Standard, Markings::default() ).contains( "[2 2] 0 d" ) );
+        let book = book( Variant::Killer, Markings { cages: cages(), ..Markings::default() } );
+        assert!( book.contains( "[2 2] 0 d" ) );
+        assert!( book.contains( "(10) Tj" ) );
+    }
+
+    #[test]
+    fn a_jigsaw_book_draws_its_regions_instead_of_blocks() {
+        // the heavy lines are set once for the regions instead of for every block edge
+        assert_eq!( book( Variant::Standard, Markings::default() ).matches( "1.8 w" ).count(), 2 );
+        assert_eq!( book( Variant::Jigsaw, Markings { regions: Some( regions() ), ..Markings::default() } ).matches( "1.8 w" ).count(), 1 );
+    }
+
+    #[test]
+    fn a_kropki_book_draws_its_dots() {
+        let links = relations::parse_links( "w:r2c1-r2c2 b:r2c2-r3c2", false ).unwrap();
+        let book = book( Variant::Kropki, Markings { links, ..Markings::default() } );
+        assert_eq!( book.matches( "\nB\n" ).count(), 2 );
+        assert!( book.contains( "\n1 g\n" ) );
+    }
+
+    #[test]
+    fn a_greater_than_book_draws_its_signs() {
+        let links = relations::parse_links( ">:r2c1-r2c2", true ).unwrap();
+        assert!( book( Variant::GreaterThan, Markings { links, ..Markings::default() } ).contains( "1.2 w" ) );
+    }
+
+    #[test]
+    fn an_even_odd_book_draws_its_parity_marks() {
+        let book = book( Variant::EvenOdd, Markings { masks: masks(), ..Markings::default() } );
+        assert_eq!( book.matches( "0.8 g" ).count(), 2 );
+    }
+
+    #[test]
+    fn an_x_book_shades_its_diagonals() {
+        assert_eq!( book( Variant::X, Markings::default() ).matches( "0.87 g" ).count(), 2 * GRID_SQRT - 1 );
+    }
+
+    #[test]
+    fn a_book_draws_thermometers_arrows_and_sandwich_sums() {
+        let book = book( Variant::Standard, Markings { clues: clues(), ..Markings::default() } );
+        assert!( book.contains( "1 J" ) );
+        assert!( book.contains( "0.55 G" ) );
+        assert!( book.contains( "(12) Tj" ) );
+    }
+}
@@ -0,0 +1,448 @@
+use crate::output;
+use crate::render::GridPiece;
+use crate::units::Board;
+use crate::{Sudoku, ALL_DIGITS, GRID_SQRT, NUM_TO_BITMAP, NUM_TO_TEXT};
+use console::{style, Key, Term};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// how often the clock is redrawn while waiting for a key
+const TICK: Duration = Duration::from_secs( 1 );
+
+// one change to a cell as (value, pencil marks) before and after, kept so it can be undone
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Move {
+    pub pos: usize,
+    pub before: (usize, usize),
+    pub after: (usize, usize),
+}
+
+// a puzzle being played: the givens, the player's entries and pencil marks
+// (a bitmap of digits per cell) and the moves that can be undone or redone
+pub struct Game {
+    pub puzzle: Vec<usize>,
+    pub entries: Vec<usize>,
+    pub pencil: Vec<usize>,
+    pub undo: Vec<Move>,
+    pub redo: Vec<Move>,
+    played: Duration,
+    started: Instant,
+}
+
+impl Game {
+
+    pub fn new( puzzle: Vec<usize> ) -> Game {
+        Game {
+            entries: vec![ 0; puzzle.len() ],
+            pencil: vec![ 0; puzzle.len() ],
+            puzzle,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            played: Duration::from_secs( 0 ),
+            started: Instant::now(),
+        }
+    }
+
+    // the givens and the entries together
+    pub fn values( &self ) -> Vec<usize> {
+        let mut values = self.entries.clone();
+        for (pos, value) in values.iter_mut().enumerate() {
+            if self.puzzle[pos] != 0 { *value = self.puzzle[pos]; }
+        }
+        values
+    }
+
+    pub fn elapsed( &self ) -> Duration {
+        self.played + self.started.elapsed()
+    }
+
+    fn apply( &mut self, pos: usize, after: (usize, usize) ) {
+        let before = ( self.entries[pos], self.pencil[pos] );
+        if self.puzzle[pos] != 0 || before == after { return; }
+        self.entries[pos] = after.0;
+        self.pencil[pos] = after.1;
+        self.undo.push( Move { pos, before, after } );
+        self.redo.clear();
+    }
+
+    pub fn enter( &mut self, pos: usize, value: usize ) {
+        self.apply( pos, ( value, self.pencil[pos] ) );
+    }
+
+    pub fn toggle_pencil( &mut self, pos: usize, value: usize ) {
+        self.apply( pos, ( self.entries[pos], self.pencil[pos] ^ NUM_TO_BITMAP[value] ) );
+    }
+
+    // clears the entry, or the pencil marks of a cell without one
+    pub fn clear( &mut self, pos: usize ) {
+        if self.entries[pos] != 0 {
+            self.apply( pos, ( 0, self.pencil[pos] ) );
+        } else {
+            self.apply( pos, ( 0, 0 ) );
+        }
+    }
+
+    pub fn undo( &mut self ) -> Option<usize> {
+        let m = self.undo.pop()?;
+        self.entries[m.pos] = m.before.0;
+        self.pencil[m.pos] = m.before.1;
+        self.redo.push( m );
+        Some( m.pos )
+    }
+
+    pub fn redo( &mut self ) -> Option<usize> {
+        let m = self.redo.pop()?;
+        self.entries[m.pos] = m.after.0;
+        self.pencil[m.pos] = m.after.1;
+        self.undo.push( m );
+        Some( m.pos )
+    }
+
+    // cells holding the same digit as one of their peers
+    pub fn conflicts( &self, peers: &[Vec<usize>] ) -> Vec<bool> {
+        let values = self.values();
+        let mut conflicts = vec![ false; values.len() ];
+        for pos in 0..values.len() {
+            if values[pos] != 0 {
+                conflicts[pos] = peers[pos].iter().any( |&p| values[p] == values[pos] );
+            }
+        }
+        conflicts
+    }
+}
+
+// a game as saved to a file: the givens and entries as puzzle lines, the
+// pencil marks as the digits of each cell and the time played in seconds
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SavedGame {
+    puzzle: String,
+    entries: String,
+    pencil: Vec<String>,
+    elapsed: u64,
+    undo: Vec<Move>,
+    redo: Vec<Move>,
+}
+
+fn format_values( values: &[usize] ) -> String {
+    values.iter().map( |&value| NUM_TO_TEXT[ value ] ).collect()
+}
+
+fn parse_values( board: &Board, line: &str, what: &str ) -> Result<Vec<usize>, String> {
+    let mut values = vec![ 0; board.size ];
+    if line.chars().count() != board.size {
+        return Err( format!( "The saved {} must have {} cells.", what, board.size ) );
+    }
+    for (pos, c) in line.chars().enumerate() {
+        values[pos] = match c {
+            '1'..='9' => c as usize - '0' as usize,
+            '.' | '0' => 0,
+            _ => return Err( format!( "The saved {} has '{}' at {}.", what, c, board.cell_name( pos ) ) ),
+        };
+    }
+    Ok(values)
+}
+
+impl Game {
+
+    pub fn to_json( &self ) -> io::Result<String> {
+        let saved = SavedGame {
+            puzzle: format_values( &self.puzzle ),
+            entries: format_values( &self.entries ),
+            pencil: self.pencil.iter().map( |&marks| pencil_text( marks ).replace( ' ', "" ) ).collect(),
+            elapsed: self.elapsed().as_secs(),
+            undo: self.undo.clone(),
+            redo: self.redo.clone(),
+        };
+        Ok( serde_json::to_string_pretty( &saved )? )
+    }
+
+    // writes the game over `filename` in one go, so a failed save keeps the last one
+    pub fn save( &self, filename: &str ) -> io::Result<()> {
+        output::write_atomically( filename, self.to_json()?.as_bytes() )
+    }
+
+    // reads a saved game of `puzzle` on `board`, checking the givens are the
+    // same, that entries and pencil marks are only in empty cells and that the
+    // undo and redo history leads back to an empty grid and forward again
+    pub(crate) fn from_json( json: &str, board: &Board, puzzle: &[usize] ) -> Result<Game, String> {
+        let saved: SavedGame = serde_json::from_str( json ).map_err( |e| format!( "The saved game is not valid: {}", e ) )?;
+        if parse_values( board, &saved.puzzle, "puzzle" )? != puzzle {
+            return Err( "The saved game is for a different puzzle.".to_string() );
+        }
+        let mut game = Game::new( puzzle.to_vec() );
+        game.entries = parse_values( board, &saved.entries, "entries" )?;
+        if saved.pencil.len() != board.size {
+            return Err( format!( "The saved pencil marks must have {} cells.", board.size ) );
+        }
+        for (pos, digits) in saved.pencil.iter().enumerate() {
+            for c in digits.chars() {
+                match c {
+                    '1'..='9' => game.pencil[pos] |= NUM_TO_BITMAP[ c as usize - '0' as usize ],
+                    _ => return Err( format!( "The saved pencil marks have '{}' at {}.", c, board.cell_name( pos ) ) ),
+                }
+            }
+        }
+        if let Some( pos ) = ( 0..board.size ).find( |&pos| puzzle[pos] != 0 && ( game.entries[pos] != 0 || game.pencil[pos] != 0 ) ) {
+            return Err( format!( "The saved game fills in the given at {}.", board.cell_name( pos ) ) );
+        }
+        let valid = |state: (usize, usize)| state.0 <= GRID_SQRT && state.1 <= ALL_DIGITS;
+        if saved.undo.iter().chain( saved.redo.iter() ).any( |m| m.pos >= board.size || puzzle[m.pos] != 0 || !valid( m.before ) || !valid( m.after ) ) {
+            return Err( "The saved history has a move that is not valid.".to_string() );
+        }
+        let state = |entries: &[usize], pencil: &[usize], pos: usize| ( entries[pos], pencil[pos] );
+        let ( mut entries, mut pencil ) = ( game.entries.clone(), game.pencil.clone() );
+        for m in saved.undo.iter().rev() {
+            if state( &entries, &pencil, m.pos ) != m.after {
+                return Err( "The saved undo history does not match the entries.".to_string() );
+            }
+            entries[m.pos] = m.before.0;
+            pencil[m.pos] = m.before.1;
+        }
+        if entries.iter().chain( pencil.iter() ).any( |&x| x != 0 ) {
+            return Err( "The saved undo history does not start from the puzzle.".to_string() );
+        }
+        let ( mut entries, mut pencil ) = ( game.entries.clone(), game.pencil.clone() );
+        for m in saved.redo.iter().rev() {
+            if state( &entries, &pencil, m.pos ) != m.before {
+                return Err( "The saved redo history does not match the entries.".to_string() );
+            }
+            entries[m.pos] = m.after.0;
+            pencil[m.pos] = m.after.1;
+        }
+        game.undo = saved.undo;
+        game.redo = saved.redo;
+        game.played = Duration::from_secs( saved.elapsed );
+        Ok(game)
+    }
+}
+
+fn format_time( time: Duration ) -> String {
+    let secs = time.as_secs();
+    format!( "{:02}:{:02}", secs / 60, secs % 60 )
+}
+
+fn pencil_text( marks: usize ) -> String {
+    ( 1..GRID_SQRT+1 ).filter( |&v| marks & NUM_TO_BITMAP[v] != 0 ).map( |v| NUM_TO_TEXT[v].to_string() ).collect::<Vec<String>>().join( " " )
+}
+
+struct Screen<'a> {
+    heading: &'a str,
+    cursor: usize,
+    pencil_mode: bool,
+    wrong: &'a [bool],
+    message: &'a str,
+}
+
+fn draw( term: &Term, sudoku: &Sudoku, game: &Game, screen: &Screen ) {
+    term.clear_screen().ok();
+    println!( "{}  {}  {}", style( screen.heading ).white(), style( format_time( game.elapsed() ) ).green(),
+        style( if screen.pencil_mode { "pencil" } else { "" } ).cyan() );
+    let values = game.values();
+    let conflicts = game.conflicts( &sudoku.constraints.peers );
+    let mut pos = 0;
+    for piece in sudoku.grid_pieces_for( &game.puzzle, &values ) {
+        match piece {
+            GridPiece::Border( s ) => print!( "{}", style( s ).green() ),
+            GridPiece::Cell { value, given, .. } => {
+                let mut text = style( format!( " {} ", NUM_TO_TEXT[ value ] ) );
+                text = if conflicts[pos] || screen.wrong[pos] { text.red().bright() } else if given { text.yellow().bright() } else { text.white() };
+                if value == 0 && game.pencil[pos] != 0 { text = style( " · ".to_string() ).cyan(); }
+                if pos == screen.cursor { text = text.reverse(); }
+                print!( "{}", text );
+                pos += 1;
+            },
+            GridPiece::Candidate { .. } => {},  // the play grid shows pencil marks below it instead
+            GridPiece::EndOfLine => println!(),
+        }
+    }
+    println!();
+    println!( " {} {}  {} {}", style( "cell" ).white(), sudoku.board().cell_name( screen.cursor ),
+        style( "pencil marks" ).white(), style( pencil_text( game.pencil[ screen.cursor ] ) ).cyan() );
+    println!( " {}", style( "arrows/hjkl move  1-9 enter  0/del clear  p pencil  u undo  r redo  c check  s save  q quit" ).white() );
+    println!( " {}", style( screen.message ).yellow() );
+}
+
+// the next cell from `cursor` in the direction (dy, dx), passing over the
+// squares outside every grid and wrapping round the edges of the board
+fn step( board: &Board, cursor: usize, dy: isize, dx: isize ) -> usize {
+    let side = board.side as isize;
+    let ( row, col ) = board.coords( cursor );
+    let ( mut row, mut col ) = ( row as isize, col as isize );
+    loop {
+        row = ( row + dy + side ) % side;
+        col = ( col + dx + side ) % side;
+        if let Some( cell ) = board.cell_at( row as usize, col as usize ) { return cell; }
+    }
+}
+
+// plays a game in the terminal until it is solved or the player quits; returns
+// whether it was solved. With a `save` file the game is written there on 's'
+// and when the player quits; a failed save is shown and the game goes on.
+pub(crate) fn play( sudoku: &Sudoku, game: &mut Game, heading: &str, solution: &[usize], save: Option<&str> ) -> io::Result<bool> {
+    let term = Term::stdout();
+    let board = sudoku.board();
+    let mut cursor = ( 0..board.size ).find( |&pos| game.puzzle[pos] == 0 ).unwrap_or( 0 );
+    let mut pencil_mode = false;
+    let mut wrong = vec![ false; board.size ];
+    let mut message = String::new();
+    // set when a save on quitting failed, so quitting again leaves without it
+    let mut quit_unsaved = false;
+    // keys are read on their own thread so the clock keeps going between them
+    let ( keys, key_events ) = mpsc::channel();
+    thread::spawn( move || {
+        let term = Term::stdout();
+        loop {
+            let key = term.read_key();
+            let failed = key.is_err();
+            if keys.send( key ).is_err() || failed { break; }
+        }
+    });
+    loop {
+        draw( &term, sudoku, game, &Screen { heading, cursor, pencil_mode, wrong: &wrong, message: &message } );
+        let values = game.values();
+        if sudoku.solves( &values ) {
+            println!( " {}", style( format!( "Solved in {}!", format_time( game.elapsed() ) ) ).green().bright() );
+            return Ok(true);
+        }
+        let key = match key_events.recv_timeout( TICK ) {
+            Ok( key ) => key?,
+            Err( RecvTimeoutError::Timeout ) => continue,
+            Err( RecvTimeoutError::Disconnected ) => return Err( io::Error::new( io::ErrorKind::UnexpectedEof, "The terminal stopped sending keys." ) ),
+        };
+        message.clear();
+        let retried_quit = std::mem::replace( &mut quit_unsaved, false );
+        match key {
+            Key::ArrowLeft | Key::Char('h') => cursor = step( board, cursor, 0, -1 ),
+            Key::ArrowRight | Key::Char('l') => cursor = step( board, cursor, 0, 1 ),
+            Key::ArrowUp | Key::Char('k') => cursor = step( board, cursor, -1, 0 ),
+            Key::ArrowDown | Key::Char('j') => cursor = step( board, cursor, 1, 0 ),
+            Key::Char( c ) if ( '1'..='9' ).contains( &c ) => {
+                let value = c as usize - '0' as usize;
+                if game.puzzle[cursor] != 0 {
+                    message = "That cell is a given.".to_string();
+                } else if pencil_mode {
+                    game.toggle_pencil( cursor, value );
+                } else {
+                    game.enter( cursor, value );
+                    wrong[cursor] = false;
+                }
+            },
+            Key::Char('0') | Key::Char('.') | Key::Del | Key::Backspace => {
+                game.clear( cursor );
+                wrong[cursor] = false;
+            },
+            Key::Char('p') => pencil_mode = !pencil_mode,
+            Key::Char('u') => match game.undo() {
+                Some( pos ) => cursor = pos,
+                None => message = "Nothing to undo.".to_string(),
+            },
+            Key::Char('r') => match game.redo() {
+                Some( pos ) => cursor = pos,
+                None => message = "Nothing to redo.".to_string(),
+            },
+            Key::Char('c') => {
+                let mut count = 0;
+                for pos in 0..board.size {
+                    wrong[pos] = game.entries[pos] != 0 && game.puzzle[pos] == 0 && game.entries[pos] != solution[pos];
+                    if wrong[pos] { count += 1; }
+                }
+                let left = values.iter().filter( |&&value| value == 0 ).count();
+                message = if count == 0 {
+                    format!( "No mistakes so far, {} cells to go.", left )
+                } else {
+                    format!( "{} cells do not match the solution.", count )
+                };
+            },
+            Key::Char('s') => match save {
+                Some( filename ) => message = match game.save( filename ) {
+                    Ok(()) => format!( "Saved to {}.", filename ),
+                    Err( e ) => format!( "Could not save to {}: {}", filename, e ),
+                },
+                None => message = "Start with --save <file> to save the game.".to_string(),
+            },
+            Key::Char('q') | Key::Escape => {
+                if let Some( filename ) = save.filter( |_| !retried_quit ) {
+                    if let Err( e ) = game.save( filename ) {
+                        message = format!( "Could not save to {}: {}. Press q again to quit without saving.", filename, e );
+                        quit_unsaved = true;
+                        continue;
+                    }
+                    println!( " {}", style( format!( "Saved to {}.", filename ) ).yellow() );
+                }
+                return Ok(false);
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{grid, SOLUTION};
+    use crate::Variant;
+    use serde_json::Value;
+
+    // the solution with its first row left to fill in
+    fn puzzle() -> Vec<usize> {
+        let mut puzzle = grid( SOLUTION ).to_vec();
+        for value in puzzle[ ..GRID_SQRT ].iter_mut() { *value = 0; }
+        puzzle
+    }
+
+    fn played() -> Game {
+        let mut game = Game::new( puzzle() );
+        game.enter( 0, 6 );
+        game.toggle_pencil( 1, 4 );
+        game.toggle_pencil( 1, 7 );
+        game.enter( 2, 9 );
+        game.undo();
+        game
+    }
+
+    fn load( json: &str ) -> Result<Game, String> {
+        Game::from_json( json, Board::of( Variant::Standard ), &puzzle() )
+    }
+
+    // the saved game as json with one field changed
+    fn edited( edit: impl Fn( &mut Value ) ) -> String {
+        let mut saved: Value = serde_json::from_str( &played().to_json().unwrap() ).unwrap();
+        edit( &mut saved );
+        saved.to_string()
+    }
+
+    #[test]
+    fn a_saved_game_loads_back_as_it_was() {
+        let game = played();
+        let json = game.to_json().unwrap();
+        let loaded = load( &json ).unwrap();
+        assert_eq!( loaded.puzzle, game.puzzle );
+        assert_eq!( loaded.entries, game.entries );
+        assert_eq!( loaded.pencil, game.pencil );
+        assert_eq!( ( loaded.undo.len(), loaded.redo.len() ), ( 3, 1 ) );
+        assert_eq!( ( loaded.undo.clone(), loaded.redo.clone() ), ( game.undo.clone(), game.redo.clone() ) );
+        assert_eq!( loaded.to_json().unwrap(), json );
+    }
+
+    #[test]
+    fn a_saved_game_that_does_not_fit_the_puzzle_is_rejected() {
+        let mut other = puzzle();
+        other[ GRID_SQRT ] = 0;
+        let json = played().to_json().unwrap();
+        assert_eq!( Game::from_json( &json, Board::of( Variant::Standard ), &other ).err().unwrap(), "The saved game is for a different puzzle." );
+
+        let error = |edit: fn( &mut Value )| load( &edited( edit ) ).err().unwrap();
+        assert!( error( |saved| saved["extra"] = Value::from( 1 ) ).starts_with( "The saved game is not valid" ) );
+        assert_eq!( error( |saved| saved["entries"] = Value::from( "6" ) ), "The saved entries must have 81 cells." );
+        assert_eq!( error( |saved| saved["pencil"][1] = Value::from( "4x" ) ), "The saved pencil marks have 'x' at r1c2." );
+        assert_eq!( error( |saved| saved["pencil"][ GRID_SQRT ] = Value::from( "1" ) ), "The saved game fills in the given at r2c1." );
+        assert_eq!( error( |saved| saved["undo"][0]["pos"] = Value::from( GRID_SQRT ) ), "The saved history has a move that is not valid." );
+        assert_eq!( error( |saved| saved["undo"][0]["after"][0] = Value::from( 5 ) ), "The saved undo history does not match the entries." );
+        assert_eq!( error( |saved| saved["undo"] = Value::Array( Vec::new() ) ), "The saved undo history does not start from the puzzle." );
+        assert_eq!( error( |saved| saved["redo"][0]["before"][0] = Value::from( 3 ) ), "The saved redo history does not match the entries." );
+    }
+}
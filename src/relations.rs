@@ -0,0 +1,237 @@
+use crate::{neighbours, GRID_SQRT, GRID_SIZE, NUM_TO_BITMAP};
+
+// how the digits of two orthogonally adjacent cells relate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Relation {
+    NonConsecutive,
+    Consecutive,  // kropki white dot
+    Double,       // kropki black dot
+    Greater,      // the first cell of the link holds the larger digit
+}
+
+impl Relation {
+    fn holds( self, a: usize, b: usize ) -> bool {
+        match self {
+            Relation::NonConsecutive => a + 1 != b && b + 1 != a,
+            Relation::Consecutive => a + 1 == b || b + 1 == a,
+            Relation::Double => a == 2 * b || b == 2 * a,
+            Relation::Greater => a > b,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Link {
+    pub a: usize,
+    pub b: usize,
+    pub relation: Relation,
+}
+
+impl Link {
+    pub fn other( &self, cell: usize ) -> usize {
+        if cell == self.a { self.b } else { self.a }
+    }
+
+    // the digits `cell` may hold when the other cell of the link may hold
+    // `candidates`, for an inequality these are the bounds it puts on the cell
+    #[allow(clippy::needless_range_loop)]
+    pub fn allowed( &self, cell: usize, candidates: usize ) -> usize {
+        let mut allowed = 0;
+        for mine in 1..GRID_SQRT+1 {
+            for theirs in 1..GRID_SQRT+1 {
+                if candidates & NUM_TO_BITMAP[theirs] == 0 { continue; }
+                let holds = if cell == self.a { self.relation.holds( mine, theirs ) } else { self.relation.holds( theirs, mine ) };
+                if holds {
+                    allowed |= NUM_TO_BITMAP[mine];
+                    break;
+                }
+            }
+        }
+        allowed
+    }
+
+    // the symbol drawn between the two cells, an inequality points at the smaller digit
+    pub fn symbol( &self ) -> Option<char> {
+        match self.relation {
+            Relation::NonConsecutive => None,
+            Relation::Consecutive => Some( '○' ),
+            Relation::Double => Some( '●' ),
+            Relation::Greater if self.b == self.a + 1 => Some( '>' ),
+            Relation::Greater if self.a == self.b + 1 => Some( '<' ),
+            Relation::Greater if self.b == self.a + GRID_SQRT => Some( '∨' ),
+            Relation::Greater => Some( '∧' ),
+        }
+    }
+}
+
+// the links each of `size` cells takes part in, as indexes into `links`
+pub fn link_index( links: &[Link], size: usize ) -> Vec<Vec<usize>> {
+    let mut links_of = vec![ Vec::new(); size ];
+    for (l, link) in links.iter().enumerate() {
+        links_of[ link.a ].push( l );
+        links_of[ link.b ].push( l );
+    }
+    links_of
+}
+
+pub fn links_satisfied( links: &[Link], solution: &[usize] ) -> bool {
+    links.iter().all( |link| link.relation.holds( solution[ link.a ], solution[ link.b ] ) )
+}
+
+// every pair of orthogonally adjacent cells
+pub fn non_consecutive_links() -> Vec<Link> {
+    let mut links = Vec::new();
+    for a in 0..GRID_SIZE {
+        for b in neighbours( a ) {
+            if b > a { links.push( Link { a, b, relation: Relation::NonConsecutive } ); }
+        }
+    }
+    links
+}
+
+// a cell reference such as "r4c5", rows and columns counted from 1
+pub fn parse_cell( cell: &str ) -> Option<usize> {
+    let cell = cell.to_lowercase();
+    let rest = cell.strip_prefix( 'r' )?;
+    let mut parts = rest.splitn( 2, 'c' );
+    let row = parts.next()?.parse::<usize>().ok()?;
+    let col = parts.next()?.parse::<usize>().ok()?;
+    if row == 0 || col == 0 || row > GRID_SQRT || col > GRID_SQRT { return None; }
+    Some( ( row - 1 ) * GRID_SQRT + col - 1 )
+}
+
+pub fn format_cell( pos: usize ) -> String {
+    format!( "r{}c{}", pos / GRID_SQRT + 1, pos % GRID_SQRT + 1 )
+}
+
+// reads a line of kropki dots such as "w:r1c1-r1c2 b:r4c5-r5c5", or with `signs`
+// a line of inequality signs such as ">:r9c1-r9c2 <:r2c3-r3c3"; a white dot joins
+// consecutive digits, a black dot digits where one is double the other and a sign
+// says which of the two cells is larger
+pub fn parse_links( line: &str, signs: bool ) -> Result<Vec<Link>, String> {
+    let what = if signs { "sign" } else { "dot" };
+    let mut links = Vec::new();
+    for field in line.split_whitespace() {
+        let invalid = || format!( "'{}' is not a valid {}.", field, what );
+        let (kind, cells) = field.split_once( ':' ).ok_or_else( invalid )?;
+        let (a, b) = cells.split_once( '-' ).ok_or_else( invalid )?;
+        let a = parse_cell( a ).ok_or_else( invalid )?;
+        let b = parse_cell( b ).ok_or_else( invalid )?;
+        if !neighbours( a ).contains( &b ) {
+            return Err( format!( "The cells of '{}' are not next to each other.", field ) );
+        }
+        links.push( match ( kind, signs ) {
+            ( "w" | "W", false ) => Link { a: a.min( b ), b: a.max( b ), relation: Relation::Consecutive },
+            ( "b" | "B", false ) => Link { a: a.min( b ), b: a.max( b ), relation: Relation::Double },
+            ( ">", true ) => Link { a, b, relation: Relation::Greater },
+            ( "<", true ) => Link { a: b, b: a, relation: Relation::Greater },
+            _ => return Err( invalid() ),
+        });
+    }
+    Ok(links)
+}
+
+pub fn format_links( links: &[Link] ) -> String {
+    links.iter()
+        .filter_map( |link| {
+            let kind = match link.relation {
+                Relation::Consecutive => "w",
+                Relation::Double => "b",
+                Relation::Greater => ">",
+                Relation::NonConsecutive => return None,
+            };
+            Some( format!( "{}:{}-{}", kind, format_cell( link.a ), format_cell( link.b ) ) )
+        })
+        .collect::<Vec<String>>()
+        .join( " " )
+}
+
+// a dot between every pair of adjacent cells of a solved grid that qualifies,
+// white where a pair could take either
+pub fn all_dots( solution: &[usize] ) -> Vec<Link> {
+    let mut links = Vec::new();
+    for a in 0..GRID_SIZE {
+        for b in neighbours( a ) {
+            if b < a { continue; }
+            if Relation::Consecutive.holds( solution[a], solution[b] ) {
+                links.push( Link { a, b, relation: Relation::Consecutive } );
+            } else if Relation::Double.holds( solution[a], solution[b] ) {
+                links.push( Link { a, b, relation: Relation::Double } );
+            }
+        }
+    }
+    links
+}
+
+// an inequality sign between every pair of adjacent cells of a solved grid
+pub fn all_signs( solution: &[usize] ) -> Vec<Link> {
+    let mut links = Vec::new();
+    for a in 0..GRID_SIZE {
+        for b in neighbours( a ) {
+            if solution[a] > solution[b] {
+                links.push( Link { a, b, relation: Relation::Greater } );
+            }
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{grid, SOLUTION};
+
+    const EVERY_DIGIT: usize = ( 1 << GRID_SQRT ) - 1;
+
+    fn bits( digits: &[usize] ) -> usize {
+        digits.iter().fold( 0, |bits, &d| bits | NUM_TO_BITMAP[d] )
+    }
+
+    #[test]
+    fn dots_and_signs_parse_for_their_own_variant_only() {
+        let dots = parse_links( "w:r1c2-r1c1 B:r4c5-r5c5", false ).unwrap();
+        assert_eq!( ( dots[0].a, dots[0].b, dots[0].relation ), ( 0, 1, Relation::Consecutive ) );
+        assert_eq!( ( dots[1].a, dots[1].b, dots[1].relation ), ( 31, 40, Relation::Double ) );
+        assert_eq!( format_links( &dots ), "w:r1c1-r1c2 b:r4c5-r5c5" );
+
+        let signs = parse_links( ">:r9c1-r9c2 <:r2c3-r3c3", true ).unwrap();
+        assert_eq!( ( signs[0].a, signs[0].b ), ( 72, 73 ) );
+        assert_eq!( ( signs[1].a, signs[1].b ), ( 20, 11 ) );
+        assert_eq!( signs[1].symbol(), Some( '∧' ) );
+
+        assert!( parse_links( ">:r1c1-r1c2", false ).is_err() );
+        assert!( parse_links( "w:r1c1-r1c2", true ).is_err() );
+        assert!( parse_links( "w:r1c1-r2c2", false ).is_err() );
+        assert!( parse_links( "w:r1c1-r1c10", false ).is_err() );
+        assert!( parse_links( "w:r1c1", false ).is_err() );
+        assert_eq!( parse_links( "", true ).unwrap().len(), 0 );
+    }
+
+    #[test]
+    fn allowed_digits_follow_the_relation() {
+        let white = Link { a: 0, b: 1, relation: Relation::Consecutive };
+        assert_eq!( white.allowed( 0, bits( &[5] ) ), bits( &[4, 6] ) );
+        assert_eq!( white.allowed( 1, bits( &[1, 9] ) ), bits( &[2, 8] ) );
+        let black = Link { a: 0, b: 1, relation: Relation::Double };
+        assert_eq!( black.allowed( 1, bits( &[4] ) ), bits( &[2, 8] ) );
+        assert_eq!( black.allowed( 0, bits( &[5, 7] ) ), 0 );
+        let apart = Link { a: 0, b: 1, relation: Relation::NonConsecutive };
+        assert_eq!( apart.allowed( 0, bits( &[5] ) ), EVERY_DIGIT & !bits( &[4, 6] ) );
+        // the larger cell must beat the smallest digit the other may hold, and the other way round
+        let greater = Link { a: 0, b: 1, relation: Relation::Greater };
+        assert_eq!( greater.allowed( 0, bits( &[3, 6] ) ), bits( &[4, 5, 6, 7, 8, 9] ) );
+        assert_eq!( greater.allowed( 1, bits( &[3, 6] ) ), bits( &[1, 2, 3, 4, 5] ) );
+        assert_eq!( greater.allowed( 1, bits( &[1] ) ), 0 );
+    }
+
+    #[test]
+    fn every_dot_and_sign_of_a_solution_holds() {
+        let solution = grid( SOLUTION );
+        let dots = all_dots( &solution );
+        assert!( !dots.is_empty() && links_satisfied( &dots, &solution ) );
+        assert_eq!( all_signs( &solution ).len(), 2 * 2 * GRID_SQRT * ( GRID_SQRT - 1 ) / 2 );
+        assert!( links_satisfied( &all_signs( &solution ), &solution ) );
+        // 7 and 6 sit side by side in the second row
+        assert!( !links_satisfied( &non_consecutive_links(), &solution ) );
+    }
+}
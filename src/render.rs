@@ -0,0 +1,944 @@
+use crate::clues::Clue;
+use crate::killer::{self, Cage};
+use crate::parity::{self, EVEN, ODD};
+use crate::relations::{Link, Relation};
+use crate::units::{Board, Constraints};
+use crate::{jigsaw, Difficulty, ALL_DIGITS, GRID_BLCK, GRID_SQRT, GRID_SIZE, NUM_TO_BITMAP, NUM_TO_TEXT};
+use std::str::FromStr;
+
+// the characters used to draw the grid lines of a puzzle
+pub struct BoxChars {
+    horizontal: &'static str,
+    vertical: &'static str,
+    top: [&'static str; 3],     // left, junction, right
+    middle: [&'static str; 3],
+    bottom: [&'static str; 3],
+}
+
+pub const UNICODE_BOX: BoxChars = BoxChars {
+    horizontal: "─",
+    vertical: "│",
+    top: ["┌", "┬", "┐"],
+    middle: ["├", "┼", "┤"],
+    bottom: ["└", "┴", "┘"],
+};
+
+pub const ASCII_BOX: BoxChars = BoxChars {
+    horizontal: "-",
+    vertical: "|",
+    top: ["+", "+", "+"],
+    middle: ["+", "+", "+"],
+    bottom: ["+", "+", "+"],
+};
+
+// a grid is laid out as a stream of pieces so the terminal can colour them
+// and the text export can print them as they are
+pub enum GridPiece {
+    Border( String ),
+    Cell { value: usize, given: bool, shaded: bool },
+    // one digit slot of an empty cell in the candidate view, 0 when the digit
+    // is not a candidate; `eliminated` marks a digit removed since the last step
+    Candidate { value: usize, eliminated: bool },
+    EndOfLine,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Text,
+    Ascii,
+    Svg,
+    Html,
+    Pdf,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str( s: &str ) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" | "txt" | "unicode" => Ok( ExportFormat::Text ),
+            "ascii" => Ok( ExportFormat::Ascii ),
+            "svg" => Ok( ExportFormat::Svg ),
+            "html" => Ok( ExportFormat::Html ),
+            "pdf" => Ok( ExportFormat::Pdf ),
+            _ => Err( format!( "Unknown export format '{}'. Use text, ascii, svg, html or pdf.", s ) ),
+        }
+    }
+}
+
+impl ExportFormat {
+    pub fn extension( self ) -> &'static str {
+        match self {
+            ExportFormat::Text | ExportFormat::Ascii => "txt",
+            ExportFormat::Svg => "svg",
+            ExportFormat::Html => "html",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+// a puzzle to export, with an optional solution printed in the answer section
+pub struct ExportItem {
+    pub number: usize,
+    pub puzzle: Vec<usize>,
+    pub solution: Option<Vec<usize>>,
+    pub difficulty: Option<Difficulty>,
+    pub markings: Markings,
+}
+
+// what a variant draws on a puzzle besides its digits: the regions of a jigsaw,
+// killer cages, kropki dots and greater-than signs, thermometers, arrows and
+// sandwich sums, and the digits every cell may hold, ALL_DIGITS when unmarked
+#[derive(Clone, Default)]
+pub struct Markings {
+    pub regions: Option<[usize; GRID_SIZE]>,
+    pub cages: Vec<Cage>,
+    pub links: Vec<Link>,
+    pub clues: Vec<Clue>,
+    pub masks: Vec<usize>,
+}
+
+// a grid to draw: its caption, the puzzle it belongs to and the values it shows
+pub type ExportGrid<'a> = (String, &'a ExportItem, &'a [usize]);
+
+fn caption( prefix: &str, item: &ExportItem ) -> String {
+    match item.difficulty {
+        Some( difficulty ) => format!( "{} #{} ({})", prefix, item.number, difficulty ),
+        None => format!( "{} #{}", prefix, item.number ),
+    }
+}
+
+fn is_block_edge( n: usize ) -> bool {
+    n.is_multiple_of( GRID_BLCK )
+}
+
+fn border_line( chars: &BoxChars, ends: [&str; 3] ) -> String {
+    border_line_of_width( chars, ends, GRID_BLCK*3 )
+}
+
+fn border_line_of_width( chars: &BoxChars, ends: [&str; 3], width: usize ) -> String {
+    let segment = chars.horizontal.repeat( width );
+    let mut line = String::new();
+    line += " ";
+    line += ends[0];
+    line += &segment;
+    for _i in 0..GRID_BLCK-1 {
+        line += ends[1];
+        line += &segment;
+    }
+    line += ends[2];
+    line += " ";
+    line
+}
+
+pub fn grid_pieces( puzzle: &[usize], solution: &[usize], shaded: &[bool], chars: &BoxChars ) -> Vec<GridPiece> {
+    let mut pieces = Vec::new();
+    pieces.push( GridPiece::Border( border_line( chars, chars.top ) ) );
+    pieces.push( GridPiece::EndOfLine );
+    for row in 0..GRID_SQRT {
+        if row > 0 && is_block_edge( row ) {
+            pieces.push( GridPiece::Border( border_line( chars, chars.middle ) ) );
+            pieces.push( GridPiece::EndOfLine );
+        }
+        pieces.push( GridPiece::Border( format!( " {}", chars.vertical ) ) );
+        for col in 0..GRID_SQRT {
+            let pos = row * GRID_SQRT + col;
+            pieces.push( GridPiece::Cell { value: solution[pos], given: puzzle[pos] != 0, shaded: shaded[pos] } );
+            if is_block_edge( col+1 ) {
+                pieces.push( GridPiece::Border( chars.vertical.to_string() ) );
+            }
+        }
+        pieces.push( GridPiece::EndOfLine );
+    }
+    pieces.push( GridPiece::Border( border_line( chars, chars.bottom ) ) );
+    pieces.push( GridPiece::EndOfLine );
+    pieces
+}
+
+// the heavy lines drawn around the regions of a jigsaw, or the blocks of a grid with dots or signs
+pub const HEAVY_BOX: BoxChars = BoxChars {
+    horizontal: "━",
+    vertical: "┃",
+    top: ["┏", "┳", "┓"],
+    middle: ["┣", "╋", "┫"],
+    bottom: ["┗", "┻", "┛"],
+};
+
+fn region_at( regions: &[usize], row: isize, col: isize ) -> Option<usize> {
+    let size = GRID_SQRT as isize;
+    if row < 0 || col < 0 || row >= size || col >= size { return None; }
+    Some( regions[ ( row * size + col ) as usize ] )
+}
+
+// a border is drawn between two cells in different regions and around the outside
+fn is_region_edge( a: Option<usize>, b: Option<usize> ) -> bool {
+    ( a.is_some() || b.is_some() ) && a != b
+}
+
+// the symbol drawn on the line between two cells, if any
+fn dot_between( dots: &[(usize, usize, char)], a: isize, b: isize ) -> Option<char> {
+    dots.iter()
+        .find( |&&(x, y, _)| ( x as isize, y as isize ) == ( a, b ) || ( x as isize, y as isize ) == ( b, a ) )
+        .map( |&(_, _, symbol)| symbol )
+}
+
+// lays out a grid with a line between every pair of cells, drawn where they
+// belong to different regions and left blank inside a region; `dots` are
+// drawn on the line between the two cells they join
+pub fn region_grid_pieces( puzzle: &[usize], solution: &[usize], shaded: &[bool], regions: &[usize], dots: &[(usize, usize, char)], chars: &BoxChars ) -> Vec<GridPiece> {
+    let mut pieces = Vec::new();
+    let size = GRID_SQRT as isize;
+    for row in 0..=size {
+        let mut line = String::from( " " );
+        for col in 0..=size {
+            let up = is_region_edge( region_at( regions, row-1, col-1 ), region_at( regions, row-1, col ) );
+            let down = is_region_edge( region_at( regions, row, col-1 ), region_at( regions, row, col ) );
+            let left = is_region_edge( region_at( regions, row-1, col-1 ), region_at( regions, row, col-1 ) );
+            let right = is_region_edge( region_at( regions, row-1, col ), region_at( regions, row, col ) );
+            let arms = up as usize | ( down as usize ) << 1 | ( left as usize ) << 2 | ( right as usize ) << 3;
+            line += junction( chars, arms );
+            if col < size {
+                match dot_between( dots, ( row-1 ) * size + col, row * size + col ) {
+                    Some( symbol ) if row > 0 && row < size => line += &format!( "{0}{1}{0}", if right { chars.horizontal } else { " " }, symbol ),
+                    _ => line += &( if right { chars.horizontal } else { " " } ).repeat( 3 ),
+                }
+            }
+        }
+        pieces.push( GridPiece::Border( line ) );
+        pieces.push( GridPiece::EndOfLine );
+        if row == size { break; }
+
+        pieces.push( GridPiece::Border( " ".to_string() ) );
+        for col in 0..size {
+            let edge = is_region_edge( region_at( regions, row, col-1 ), region_at( regions, row, col ) );
+            let pos = ( row * size + col ) as usize;
+            match dot_between( dots, pos as isize - 1, pos as isize ) {
+                Some( symbol ) if col > 0 => pieces.push( GridPiece::Border( symbol.to_string() ) ),
+                _ => pieces.push( GridPiece::Border( if edge { chars.vertical } else { " " }.to_string() ) ),
+            }
+            pieces.push( GridPiece::Cell { value: solution[pos], given: puzzle[pos] != 0, shaded: shaded[pos] } );
+        }
+        pieces.push( GridPiece::Border( chars.vertical.to_string() ) );
+        pieces.push( GridPiece::EndOfLine );
+    }
+    pieces
+}
+
+// the character where grid lines meet, by the arms it has: up 1, down 2, left 4, right 8
+fn junction( chars: &BoxChars, arms: usize ) -> &'static str {
+    match arms {
+        0 => " ",
+        1..=3 => chars.vertical,
+        4 | 8 | 12 => chars.horizontal,
+        5 => chars.bottom[2],
+        6 => chars.top[2],
+        7 => chars.middle[2],
+        9 => chars.bottom[0],
+        10 => chars.top[0],
+        11 => chars.middle[0],
+        13 => chars.bottom[1],
+        14 => chars.top[1],
+        _ => chars.middle[1],
+    }
+}
+
+// lays out a square board of `blocks` by `blocks` blocks where some blocks may
+// be missing, as in samurai; `cell` gives the value and whether it is a given
+// for every cell of a block that is part of the board, and None elsewhere
+pub fn block_grid_pieces( blocks: usize, chars: &BoxChars, cell: impl Fn( usize, usize ) -> Option<(usize, bool)> ) -> Vec<GridPiece> {
+    let size = blocks as isize;
+    let block_at = |brow: isize, bcol: isize| -> Option<usize> {
+        if brow < 0 || bcol < 0 || brow >= size || bcol >= size { return None; }
+        let ( row, col ) = ( brow as usize * GRID_BLCK, bcol as usize * GRID_BLCK );
+        cell( row, col ).map( |_| brow as usize * blocks + bcol as usize )
+    };
+    let mut pieces = Vec::new();
+    for brow in 0..=size {
+        let mut line = String::from( " " );
+        for bcol in 0..=size {
+            let up = is_region_edge( block_at( brow-1, bcol-1 ), block_at( brow-1, bcol ) );
+            let down = is_region_edge( block_at( brow, bcol-1 ), block_at( brow, bcol ) );
+            let left = is_region_edge( block_at( brow-1, bcol-1 ), block_at( brow, bcol-1 ) );
+            let right = is_region_edge( block_at( brow-1, bcol ), block_at( brow, bcol ) );
+            let arms = up as usize | ( down as usize ) << 1 | ( left as usize ) << 2 | ( right as usize ) << 3;
+            line += junction( chars, arms );
+            if bcol < size {
+                line += &( if right { chars.horizontal } else { " " } ).repeat( GRID_BLCK*3 );
+            }
+        }
+        pieces.push( GridPiece::Border( line ) );
+        pieces.push( GridPiece::EndOfLine );
+        if brow == size { break; }
+
+        for r in 0..GRID_BLCK {
+            let row = brow as usize * GRID_BLCK + r;
+            pieces.push( GridPiece::Border( " ".to_string() ) );
+            for bcol in 0..=size {
+                let edge = is_region_edge( block_at( brow, bcol-1 ), block_at( brow, bcol ) );
+                pieces.push( GridPiece::Border( if edge { chars.vertical } else { " " }.to_string() ) );
+                if bcol == size { break; }
+                for c in 0..GRID_BLCK {
+                    match cell( row, bcol as usize * GRID_BLCK + c ) {
+                        Some( ( value, given ) ) => pieces.push( GridPiece::Cell { value, given, shaded: false } ),
+                        None => pieces.push( GridPiece::Border( "   ".to_string() ) ),
+                    }
+                }
+            }
+            pieces.push( GridPiece::EndOfLine );
+        }
+    }
+    pieces
+}
+
+// every empty cell drawn as a block of its candidates, digit d at row
+// (d-1)/GRID_BLCK and column (d-1)%GRID_BLCK; filled cells show their digit in
+// the middle. Digits in `previous` but not in `candidates` are marked eliminated.
+#[allow(clippy::needless_range_loop)]
+pub fn candidate_grid_pieces( puzzle: &[usize], solution: &[usize], shaded: &[bool],
+    candidates: &[usize], previous: Option<&[usize]> ) -> Vec<GridPiece> {
+    let chars = &UNICODE_BOX;
+    let width = GRID_BLCK * ( GRID_BLCK + 1 ) + 1;
+    let mut pieces = Vec::new();
+    let line = |pieces: &mut Vec<GridPiece>, ends: [&str; 3]| {
+        pieces.push( GridPiece::Border( border_line_of_width( chars, ends, width ) ) );
+        pieces.push( GridPiece::EndOfLine );
+    };
+    line( &mut pieces, chars.top );
+    for row in 0..GRID_SQRT {
+        if row > 0 && is_block_edge( row ) {
+            line( &mut pieces, chars.middle );
+        } else if row > 0 {
+            // a gap between the rows of cells inside a block
+            pieces.push( GridPiece::Border( format!( " {}", ( chars.vertical.to_string() + &" ".repeat( width ) ).repeat( GRID_BLCK ) + chars.vertical + " " ) ) );
+            pieces.push( GridPiece::EndOfLine );
+        }
+        for sub_row in 0..GRID_BLCK {
+            pieces.push( GridPiece::Border( format!( " {} ", chars.vertical ) ) );
+            for col in 0..GRID_SQRT {
+                let pos = row * GRID_SQRT + col;
+                if solution[pos] != 0 {
+                    if sub_row == GRID_BLCK / 2 {
+                        pieces.push( GridPiece::Cell { value: solution[pos], given: puzzle[pos] != 0, shaded: shaded[pos] } );
+                    } else {
+                        pieces.push( GridPiece::Border( " ".repeat( GRID_BLCK ) ) );
+                    }
+                } else {
+                    for d in sub_row * GRID_BLCK + 1..( sub_row + 1 ) * GRID_BLCK + 1 {
+                        let bitmap = NUM_TO_BITMAP[ d ];
+                        let was = previous.is_some_and( |previous| previous[pos] & bitmap != 0 );
+                        let is = candidates[pos] & bitmap != 0;
+                        pieces.push( GridPiece::Candidate { value: if is || was { d } else { 0 }, eliminated: was && !is } );
+                    }
+                }
+                pieces.push( GridPiece::Border( if is_block_edge( col+1 ) { format!( " {} ", chars.vertical ) } else { " ".to_string() } ) );
+            }
+            pieces.push( GridPiece::EndOfLine );
+        }
+    }
+    line( &mut pieces, chars.bottom );
+    pieces
+}
+
+// a single grid as grid_pieces lays it out, a board of several grids as blocks
+pub fn board_pieces( board: &Board, puzzle: &[usize], solution: &[usize], shaded: &[bool], chars: &BoxChars ) -> Vec<GridPiece> {
+    if board.grids.len() == 1 { return grid_pieces( puzzle, solution, shaded, chars ); }
+    block_grid_pieces( board.side / GRID_BLCK, chars, |row, col| board.cell_at( row, col ).map( |cell| ( solution[cell], puzzle[cell] != 0 ) ) )
+}
+
+// the symbol of a dot or sign in plain ascii
+fn ascii_symbol( symbol: char ) -> char {
+    match symbol {
+        '○' => 'o',
+        '●' => '*',
+        '∨' => 'v',
+        '∧' => '^',
+        _ => symbol,
+    }
+}
+
+// a grid as text, shaded cells in brackets, with the regions of a jigsaw and any
+// dots or signs drawn in it and its cages, parity marks and clues written out under it
+pub fn text_grid( board: &Board, puzzle: &[usize], solution: &[usize], shaded: &[bool], markings: &Markings, chars: &BoxChars ) -> Result<String, String> {
+    let pieces = if markings.regions.is_some() || !markings.links.is_empty() {
+        let regions = markings.regions.unwrap_or_else( jigsaw::standard_regions );
+        let ascii = chars.vertical == ASCII_BOX.vertical;
+        let dots: Vec<(usize, usize, char)> = markings.links.iter()
+            .filter_map( |link| link.symbol().map( |symbol| ( link.a, link.b, if ascii { ascii_symbol( symbol ) } else { symbol } ) ) )
+            .collect();
+        region_grid_pieces( puzzle, solution, shaded, &regions, &dots, if ascii { chars } else { &HEAVY_BOX } )
+    } else {
+        board_pieces( board, puzzle, solution, shaded, chars )
+    };
+    let mut text = String::new();
+    for piece in pieces {
+        match piece {
+            GridPiece::Border( s ) => text += &s,
+            GridPiece::Cell { value, shaded: false, .. } => text += &format!( " {} ", NUM_TO_TEXT[ value ] ),
+            GridPiece::Cell { value, shaded: true, .. } => text += &format!( "[{}]", NUM_TO_TEXT[ value ] ),
+            GridPiece::Candidate { value, .. } => text.push( if value == 0 { ' ' } else { NUM_TO_TEXT[ value ] } ),
+            GridPiece::EndOfLine => text += "\n",
+        }
+    }
+    text += &text_legend( board, markings )?;
+    Ok(text)
+}
+
+// writes a map of one label per cell a row to a line, then what the labels stand for
+fn text_map( heading: &str, line: &str ) -> String {
+    let ( labels, definitions ) = line.split_at( GRID_SIZE );
+    let mut text = format!( " {}:\n", heading );
+    for row in labels.as_bytes().chunks( GRID_SQRT ) {
+        let row: Vec<String> = row.iter().map( |&label| ( label as char ).to_string() ).collect();
+        text += &format!( "   {}\n", row.join( " " ) );
+    }
+    if !definitions.trim().is_empty() {
+        text += &format!( "   {}\n", definitions.trim() );
+    }
+    text
+}
+
+// the cages, parity marks and clues of a puzzle, written out under its grid
+fn text_legend( board: &Board, markings: &Markings ) -> Result<String, String> {
+    let mut text = String::new();
+    if !markings.cages.is_empty() {
+        text += &text_map( "Cages", &killer::format_cages( &markings.cages ) );
+    }
+    if markings.masks.iter().any( |&mask| mask != ALL_DIGITS ) {
+        text += &text_map( "Parity", &parity::format_masks( &markings.masks )? );
+    }
+    let names = |cells: &[usize]| cells.iter().map( |&cell| board.cell_name( cell ) ).collect::<Vec<_>>().join( " " );
+    for clue in markings.clues.iter() {
+        text += &match clue {
+            Clue::Thermometer( cells ) => format!( " Thermometer from the bulb: {}\n", names( cells ) ),
+            Clue::Arrow { circle, cells } => format!( " Arrow from the circle at {}: {}\n", board.cell_name( *circle ), names( cells ) ),
+            Clue::Sandwich { cells, sum } => {
+                let ( line, n ) = sandwich_line( board, cells );
+                format!( " Sandwich in {} {}: {}\n", line, n, sum )
+            },
+        };
+    }
+       Ok(text)
+}
+
+// a point on the board measured in cells from its top left corner, x to the
+// right and y down; the svg and pdf exports scale it to their cell size
+pub type Point = (f32, f32);
+
+// how far the dashed outline of a cage sits inside its cells
+const CAGE_INSET: f32 = 0.1;
+
+// the radius of a thermometer's bulb and of an arrow's circle
+pub const BULB_RADIUS: f32 = 0.35;
+pub const CIRCLE_RADIUS: f32 = 0.4;
+
+fn centre( board: &Board, cell: usize ) -> Point {
+    let ( row, col ) = board.coords( cell );
+    ( col as f32 + 0.5, row as f32 + 0.5 )
+}
+
+// the outline of a cage as lines a little inside its cells, joined up where the
+// cage turns a corner
+pub fn cage_outline( board: &Board, cells: &[usize] ) -> Vec<(Point, Point)> {
+    let inside = |row: isize, col: isize| {
+        row >= 0 && col >= 0 && board.cell_at( row as usize, col as usize ).is_some_and( |cell| cells.contains( &cell ) )
+    };
+    // an edge running towards `at` (in the direction `step`) stops short of a cell
+    // outside the cage, goes past the line where the cage wraps round a corner
+    // and runs into the same edge of the next cell otherwise
+    let end = |along: bool, diagonal: bool, at: f32, step: f32| {
+        if !along { at - step * CAGE_INSET } else if diagonal { at + step * CAGE_INSET } else { at }
+    };
+    let mut lines = Vec::new();
+    for &cell in cells.iter() {
+        let ( row, col ) = board.coords( cell );
+        let ( r, c ) = ( row as isize, col as isize );
+        let ( y, x ) = ( row as f32, col as f32 );
+        for &(dr, edge) in [ ( -1, y + CAGE_INSET ), ( 1, y + 1.0 - CAGE_INSET ) ].iter() {
+            if inside( r + dr, c ) { continue; }
+            let x1 = end( inside( r, c-1 ), inside( r+dr, c-1 ), x, -1.0 );
+            let x2 = end( inside( r, c+1 ), inside( r+dr, c+1 ), x + 1.0, 1.0 );
+            lines.push( ( ( x1, edge ), ( x2, edge ) ) );
+        }
+        for &(dc, edge) in [ ( -1, x + CAGE_INSET ), ( 1, x + 1.0 - CAGE_INSET ) ].iter() {
+            if inside( r, c + dc ) { continue; }
+            let y1 = end( inside( r-1, c ), inside( r-1, c+dc ), y, -1.0 );
+            let y2 = end( inside( r+1, c ), inside( r+1, c+dc ), y + 1.0, 1.0 );
+            lines.push( ( ( edge, y1 ), ( edge, y2 ) ) );
+        }
+    }
+    lines
+}
+
+// the top left corner of a cage's first cell, where its sum is written
+pub fn cage_corner( board: &Board, cells: &[usize] ) -> Point {
+    let ( row, col ) = cells.iter().map( |&cell| board.coords( cell ) ).min().unwrap_or( ( 0, 0 ) );
+    ( col as f32, row as f32 )
+}
+
+// how a kropki dot or a greater-than sign is drawn on the line between its cells
+pub enum LinkMark {
+    Dot { centre: Point, black: bool },
+    // the ends of the two arms of a sign and its point, towards the smaller digit
+    Chevron( [Point; 3] ),
+}
+
+pub fn link_mark( board: &Board, link: &Link ) -> Option<LinkMark> {
+    let ( a, b ) = ( centre( board, link.a ), centre( board, link.b ) );
+    let mid = ( ( a.0 + b.0 ) / 2.0, ( a.1 + b.1 ) / 2.0 );
+    match link.relation {
+        Relation::Consecutive => Some( LinkMark::Dot { centre: mid, black: false } ),
+        Relation::Double => Some( LinkMark::Dot { centre: mid, black: true } ),
+        Relation::Greater => {
+            // a step of one cell from the larger digit to the smaller, and one across it
+            let ( ux, uy ) = ( b.0 - a.0, b.1 - a.1 );
+            let ( vx, vy ) = ( -uy, ux );
+            let ( depth, width ) = ( 0.08, 0.12 );
+            Some( LinkMark::Chevron( [
+                ( mid.0 - ux * depth + vx * width, mid.1 - uy * depth + vy * width ),
+                ( mid.0 + ux * depth, mid.1 + uy * depth ),
+                ( mid.0 - ux * depth - vx * width, mid.1 - uy * depth - vy * width ),
+            ] ) )
+        },
+        Relation::NonConsecutive => None,
+    }
+}
+
+// the path of a thermometer or arrow through the centres of its cells, an arrow
+// setting out from the edge of its circle
+pub fn clue_path( board: &Board, clue: &Clue ) -> Vec<Point> {
+    match clue {
+        Clue::Thermometer( cells ) => cells.iter().map( |&cell| centre( board, cell ) ).collect(),
+        Clue::Arrow { circle, cells } => {
+            let ( cx, cy ) = centre( board, *circle );
+            let ( fx, fy ) = centre( board, cells[0] );
+            let length = ( ( fx - cx ).powi( 2 ) + ( fy - cy ).powi( 2 ) ).sqrt();
+            let start = ( cx + ( fx - cx ) / length * CIRCLE_RADIUS, cy + ( fy - cy ) / length * CIRCLE_RADIUS );
+            std::iter::once( start ).chain( cells.iter().map( |&cell| centre( board, cell ) ) ).collect()
+        },
+        Clue::Sandwich { .. } => Vec::new(),
+    }
+}
+
+// the two barbs of the head at the end of an arrow's path and its tip
+pub fn arrow_head( path: &[Point] ) -> [Point; 3] {
+    let ( ( px, py ), ( x, y ) ) = ( path[ path.len()-2 ], path[ path.len()-1 ] );
+    let length = ( ( x - px ).powi( 2 ) + ( y - py ).powi( 2 ) ).sqrt();
+    let ( ux, uy ) = ( ( x - px ) / length * 0.2, ( y - py ) / length * 0.2 );
+    [ ( x - ux - uy, y - uy + ux ), ( x, y ), ( x - ux + uy, y - uy - ux ) ]
+}
+
+// whether a sandwich runs along a row or a column, and which one from 1
+fn sandwich_line( board: &Board, cells: &[usize] ) -> (&'static str, usize) {
+    let ( first, second ) = ( board.coords( cells[0] ), board.coords( cells[1] ) );
+    if first.0 == second.0 { ( "row", first.0 + 1 ) } else { ( "column", first.1 + 1 ) }
+}
+
+// where the sum of a sandwich is written: right of its row or under its column
+pub fn sandwich_label( board: &Board, cells: &[usize] ) -> Point {
+    let outside = board.side as f32 + 0.4;
+    match sandwich_line( board, cells ) {
+        ( "row", n ) => ( outside, n as f32 - 0.5 ),
+        ( _, n ) => ( n as f32 - 0.5, outside ),
+    }
+}
+
+// the digits of a digit mask other than even or odd, written small in its cell
+pub fn mask_digits( mask: usize ) -> String {
+    ( 1..GRID_SQRT+1 ).filter( |&d| mask & NUM_TO_BITMAP[d] != 0 ).map( |d| NUM_TO_TEXT[d] ).collect()
+}
+
+const SVG_CELL: usize = 40;
+const SVG_GRID: usize = SVG_CELL * GRID_SQRT;
+const SVG_MARGIN: usize = 30;
+
+fn svg_length( cells: f32 ) -> f32 {
+    cells * SVG_CELL as f32
+}
+
+fn svg_points( points: &[Point] ) -> String {
+    points.iter().map( |&(x, y)| format!( "{},{}", svg_length( x ), svg_length( y ) ) ).collect::<Vec<_>>().join( " " )
+}
+
+// draws every grid of a board at its place with the markings of its variant,
+// the board's top left corner at (x, y)
+fn svg_board( board: &Board, shaded: &[bool], item: &ExportItem, values: &[usize], x: usize, y: usize ) -> String {
+    let mut svg = format!( "<g transform=\"translate({},{})\">\n", x, y );
+    for grid in board.grids.iter() {
+        svg += &format!( "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"white\"/>\n", grid.left * SVG_CELL, grid.top * SVG_CELL, SVG_GRID );
+    }
+    svg += &svg_underlay( board, shaded, &item.markings );
+    for (g, grid) in board.grids.iter().enumerate() {
+        let ( puzzle, values ) = ( board.grid_values( &item.puzzle, g ), board.grid_values( values, g ) );
+        svg += &svg_grid( &puzzle, &values, item.markings.regions.as_ref(), grid.left * SVG_CELL, grid.top * SVG_CELL );
+    }
+    svg += &svg_overlay( board, &item.markings );
+    svg += "</g>\n";
+    svg
+}
+
+// the shading of extra units, parity marks, thermometers and arrows, which go
+// under the grid lines and digits
+fn svg_underlay( board: &Board, shaded: &[bool], markings: &Markings ) -> String {
+    let mut svg = String::new();
+    for (cell, &shade) in shaded.iter().enumerate() {
+        let ( row, col ) = board.coords( cell );
+        let ( x, y ) = ( col * SVG_CELL, row * SVG_CELL );
+        if shade {
+            svg += &format!( "<rect class=\"shaded\" x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"#ddd\"/>\n", x, y, SVG_CELL );
+        }
+        match markings.masks.get( cell ).copied().unwrap_or( ALL_DIGITS ) {
+            ALL_DIGITS => (),
+            EVEN => svg += &format!( "<rect class=\"even\" x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"#ccc\"/>\n", x + 6, y + 6, SVG_CELL - 12 ),
+            ODD => svg += &format!( "<circle class=\"odd\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"#ccc\"/>\n", x + SVG_CELL / 2, y + SVG_CELL / 2, SVG_CELL / 2 - 5 ),
+            mask => svg += &format!( "<text class=\"small\" x=\"{}\" y=\"{}\">{}</text>\n", x + 3, y + SVG_CELL - 4, mask_digits( mask ) ),
+        }
+    }
+    for clue in markings.clues.iter() {
+        let path = clue_path( board, clue );
+        match clue {
+            Clue::Thermometer( _ ) => {
+                svg += &format!( "<circle class=\"thermometer\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"#ccc\"/>\n", svg_length( path[0].0 ), svg_length( path[0].1 ), svg_length( BULB_RADIUS ) );
+                svg += &format!( "<polyline points=\"{}\" fill=\"none\" stroke=\"#ccc\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n", svg_points( &path ), svg_length( 0.3 ) );
+            },
+            Clue::Arrow { circle, .. } => {
+                let ( cx, cy ) = centre( board, *circle );
+                svg += &format!( "<circle class=\"arrow\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"#999\" stroke-width=\"2\"/>\n", svg_length( cx ), svg_length( cy ), svg_length( CIRCLE_RADIUS ) );
+                svg += &format!( "<polyline points=\"{}\" fill=\"none\" stroke=\"#999\" stroke-width=\"2\"/>\n", svg_points( &path ) );
+                svg += &format!( "<polyline points=\"{}\" fill=\"none\" stroke=\"#999\" stroke-width=\"2\"/>\n", svg_points( &arrow_head( &path ) ) );
+            },
+            Clue::Sandwich { .. } => (),
+        }
+    }
+    svg
+}
+
+// killer cages with their sums, dots, signs and sandwich sums, which go over
+// the grid lines
+fn svg_overlay( board: &Board, markings: &Markings ) -> String {
+    let mut svg = String::new();
+    for cage in markings.cages.iter() {
+        svg += "<g class=\"cage\" stroke=\"black\" stroke-width=\"1\" stroke-dasharray=\"3 3\">\n";
+        for ( (x1, y1), (x2, y2) ) in cage_outline( board, &cage.cells ) {
+            svg += &format!( "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>\n", svg_length( x1 ), svg_length( y1 ), svg_length( x2 ), svg_length( y2 ) );
+        }
+        svg += "</g>\n";
+        let ( x, y ) = cage_corner( board, &cage.cells );
+        let sum = cage.sum.to_string();
+        svg += &format!( "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"11\" fill=\"white\"/>\n", svg_length( x ) + 1.0, svg_length( y ) + 1.0, sum.len() * 6 + 3 );
+        svg += &format!( "<text class=\"small\" x=\"{}\" y=\"{}\">{}</text>\n", svg_length( x ) + 2.0, svg_length( y ) + 10.0, sum );
+    }
+    for link in markings.links.iter() {
+        match link_mark( board, link ) {
+            Some( LinkMark::Dot { centre: (x, y), black } ) => {
+                svg += &format!( "<circle class=\"dot\" cx=\"{}\" cy=\"{}\" r=\"5\" fill=\"{}\" stroke=\"black\"/>\n", svg_length( x ), svg_length( y ), if black { "black" } else { "white" } );
+            },
+            Some( LinkMark::Chevron( points ) ) => {
+                svg += &format!( "<polyline class=\"sign\" points=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"2\"/>\n", svg_points( &points ) );
+            },
+            None => (),
+        }
+    }
+    for clue in markings.clues.iter() {
+        if let Clue::Sandwich { cells, sum } = clue {
+            let ( x, y ) = sandwich_label( board, cells );
+            svg += &format!( "<text class=\"sandwich\" x=\"{}\" y=\"{}\">{}</text>\n", svg_length( x ), svg_length( y ) + 5.0, sum );
+        }
+    }
+    svg
+}
+
+// draws one grid with its top left corner at (x, y), heavy lines around its
+// blocks or the regions of a jigsaw
+pub fn svg_grid( puzzle: &[usize], solution: &[usize], regions: Option<&[usize; GRID_SIZE]>, x: usize, y: usize ) -> String {
+    let mut svg = format!( "<g transform=\"translate({},{})\">\n", x, y );
+    for n in 1..GRID_SQRT {
+        let heavy = regions.is_none() && is_block_edge( n );
+        let (width, colour) = if heavy { ( 2.5, "black" ) } else { ( 1.0, "#999" ) };
+        let p = n * SVG_CELL;
+        svg += &format!( "<line x1=\"{0}\" y1=\"0\" x2=\"{0}\" y2=\"{1}\" stroke=\"{2}\" stroke-width=\"{3}\"/>\n", p, SVG_GRID, colour, width );
+        svg += &format!( "<line x1=\"0\" y1=\"{0}\" x2=\"{1}\" y2=\"{0}\" stroke=\"{2}\" stroke-width=\"{3}\"/>\n", p, SVG_GRID, colour, width );
+    }
+    if let Some( regions ) = regions {
+        for pos in 0..GRID_SIZE {
+            let ( row, col ) = ( pos / GRID_SQRT, pos % GRID_SQRT );
+            let ( cx, cy ) = ( col * SVG_CELL, row * SVG_CELL );
+            if col + 1 < GRID_SQRT && regions[pos] != regions[pos+1] {
+                svg += &format!( "<line class=\"region\" x1=\"{0}\" y1=\"{1}\" x2=\"{0}\" y2=\"{2}\" stroke=\"black\" stroke-width=\"2.5\" stroke-linecap=\"square\"/>\n", cx + SVG_CELL, cy, cy + SVG_CELL );
+            }
+            if row + 1 < GRID_SQRT && regions[pos] != regions[pos+GRID_SQRT] {
+                svg += &format!( "<line class=\"region\" x1=\"{0}\" y1=\"{1}\" x2=\"{2}\" y2=\"{1}\" stroke=\"black\" stroke-width=\"2.5\" stroke-linecap=\"square\"/>\n", cx, cy + SVG_CELL, cx + SVG_CELL );
+            }
+        }
+    }
+    svg += &format!( "<rect width=\"{0}\" height=\"{0}\" fill=\"none\" stroke=\"black\" stroke-width=\"3\"/>\n", SVG_GRID );
+    for pos in 0..GRID_SIZE {
+        if solution[pos] == 0 { continue; }
+        let cx = ( pos % GRID_SQRT ) * SVG_CELL + SVG_CELL / 2;
+        let cy = ( pos / GRID_SQRT ) * SVG_CELL + SVG_CELL * 7 / 10;
+        let (weight, colour) = if puzzle[pos] != 0 { ( "bold", "black" ) } else { ( "normal", "#1a5fb4" ) };
+        svg += &format!( "<text x=\"{}\" y=\"{}\" font-weight=\"{}\" fill=\"{}\">{}</text>\n", cx, cy, weight, colour, NUM_TO_TEXT[ solution[pos] ] );
+    }
+    svg += "</g>\n";
+    svg
+}
+
+// lays the grids out in rows of `columns`, each with a caption above it
+fn svg_document( board: &Board, shaded: &[bool], grids: &[ExportGrid], columns: usize ) -> String {
+    let columns = columns.max( 1 ).min( grids.len().max( 1 ) );
+    let rows = grids.len().div_ceil( columns );
+    let side = board.side * SVG_CELL;
+    let width = columns * ( side + SVG_MARGIN ) + SVG_MARGIN;
+    let height = rows * ( side + SVG_MARGIN * 2 ) + SVG_MARGIN;
+    let mut svg = format!( "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n", width, height );
+    svg += "<style>text { font-family: sans-serif; font-size: 24px; text-anchor: middle; } text.caption { font-size: 16px; text-anchor: start; } text.small { font-size: 10px; text-anchor: start; } text.sandwich { font-size: 14px; }</style>\n";
+    for (i, (caption, item, values)) in grids.iter().enumerate() {
+        let x = SVG_MARGIN + ( i % columns ) * ( side + SVG_MARGIN );
+        let y = SVG_MARGIN * 2 + ( i / columns ) * ( side + SVG_MARGIN * 2 );
+        svg += &format!( "<text class=\"caption\" x=\"{}\" y=\"{}\">{}</text>\n", x, y - 8, caption );
+        svg += &svg_board( board, shaded, item, values, x, y );
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+fn puzzle_grids( items: &[ExportItem] ) -> Vec<ExportGrid<'_>> {
+    items.iter().map( |item| ( caption( "Puzzle", item ), item, &item.puzzle[..] ) ).collect()
+}
+
+fn solution_grids( items: &[ExportItem] ) -> Vec<ExportGrid<'_>> {
+    items.iter()
+        .filter_map( |item| item.solution.as_ref().map( |solution| ( caption( "Solution", item ), item, &solution[..] ) ) )
+        .collect()
+}
+
+pub fn export_text( items: &[ExportItem], board: &Board, constraints: &Constraints, chars: &BoxChars ) -> Result<String, String> {
+    let shaded = constraints.shaded_cells();
+    let mut text = String::new();
+    for (caption, item, values) in puzzle_grids( items ).into_iter().chain( solution_grids( items ) ) {
+        text += &format!( "{}\n", caption );
+        text += &text_grid( board, &item.puzzle, values, &shaded, &item.markings, chars )?;
+        text += "\n";
+    }
+    Ok(text)
+}
+
+pub fn export_svg( items: &[ExportItem], board: &Board, constraints: &Constraints ) -> String {
+    let mut grids = puzzle_grids( items );
+    grids.extend( solution_grids( items ) );
+    svg_document( board, &constraints.shaded_cells(), &grids, 2 )
+}
+
+// a printable page per `per_page` puzzles, followed by the solutions on their own pages
+pub fn export_html( items: &[ExportItem], board: &Board, constraints: &Constraints, per_page: usize ) -> String {
+    let per_page = per_page.max( 1 );
+    let columns = if per_page == 1 { 1 } else { 2 };
+    let shaded = constraints.shaded_cells();
+    let mut html = String::new();
+    html += "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Sudoku Puzzles</title>\n";
+    html += "<style>\n";
+    html += "body { font-family: sans-serif; margin: 0; }\n";
+    html += ".page { page-break-after: always; break-after: page; padding: 1cm; }\n";
+    html += ".page:last-child { page-break-after: auto; break-after: auto; }\n";
+    html += ".page h1 { font-size: 18px; }\n";
+    html += ".page svg { max-width: 100%; height: auto; }\n";
+    html += "</style>\n</head>\n<body>\n";
+    let sections = [ ( "Puzzles", puzzle_grids( items ) ), ( "Solutions", solution_grids( items ) ) ];
+    for (title, grids) in sections.iter() {
+        for (page, chunk) in grids.chunks( per_page ).enumerate() {
+            html += &format!( "<div class=\"page\">\n<h1>{} &mdash; page {}</h1>\n", title, page+1 );
+            html += &svg_document( board, &shaded, chunk, columns );
+            html += "</div>\n";
+        }
+    }
+    html += "</body>\n</html>\n";
+    html
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::tests::{grid, SOLUTION};
+    use crate::{clues, relations, Variant};
+
+    // the solution with its first row left empty, carrying the markings of a variant
+    pub fn item( markings: Markings ) -> ExportItem {
+        let mut puzzle = grid( SOLUTION ).to_vec();
+        for value in puzzle[ ..GRID_SQRT ].iter_mut() { *value = 0; }
+        ExportItem { number: 1, puzzle, solution: None, difficulty: None, markings }
+    }
+
+    pub fn constraints( variant: Variant ) -> Constraints {
+        Constraints::new( variant, &jigsaw::standard_regions(), &[] )
+    }
+
+    // the two cells of the first row of a cage of 10, the rest outside every cage
+    pub fn cages() -> Vec<Cage> {
+        killer::parse_cages( &format!( "aa{}", ".".repeat( GRID_SIZE - 2 ) ), &[ "a=10" ] ).unwrap()
+    }
+
+    // the blocks of a standard grid with r1c3 and r2c4 traded between the first two
+    pub fn regions() -> [usize; GRID_SIZE] {
+        let mut regions = jigsaw::standard_regions();
+        regions.swap( 2, GRID_SQRT + 3 );
+        regions
+    }
+
+    pub fn masks() -> Vec<usize> {
+        let mut masks = vec![ ALL_DIGITS; GRID_SIZE ];
+        masks[0] = EVEN;
+        masks[1] = ODD;
+        masks
+    }
+
+    pub fn clues() -> Vec<Clue> {
+        let specs = clues::parse_clue_file( r#"[ { "thermometers": [ ["r2c1", "r2c2", "r3c2"] ],
+            "arrows": [ { "circle": "r5c5", "cells": ["r5c6", "r6c6"] } ],
+            "sandwiches": { "rows": [ null, 12 ] } } ]"# ).unwrap();
+        clues::parse_clues( &specs[0] ).unwrap()
+    }
+
+    fn svg( variant: Variant, markings: Markings ) -> String {
+        export_svg( &[ item( markings ) ], Board::of( variant ), &constraints( variant ) )
+    }
+
+    fn text( variant: Variant, markings: Markings, chars: &BoxChars ) -> String {
+        export_text( &[ item( markings ) ], Board::of( variant ), &constraints( variant ), chars ).unwrap()
+    }
+
+    // the text of every line of a piece list, one char per column; candidates are
+    // shown with eliminated ones as 'x'
+    fn piece_lines( pieces: &[GridPiece] ) -> Vec<Vec<char>> {
+        let mut lines = vec![ String::new() ];
+        for piece in pieces {
+            let text = lines.last_mut().unwrap();
+            match piece {
+                GridPiece::Border( s ) => *text += s,
+                GridPiece::Cell { value, .. } => *text += &format!( " {} ", NUM_TO_TEXT[ *value ] ),
+                GridPiece::Candidate { eliminated: true, .. } => text.push( 'x' ),
+                GridPiece::Candidate { value, .. } => text.push( if *value == 0 { '.' } else { NUM_TO_TEXT[ *value ] } ),
+                GridPiece::EndOfLine => lines.push( String::new() ),
+            }
+        }
+        lines.iter().map( |line| line.chars().collect() ).collect()
+    }
+
+    #[test]
+    fn a_cell_of_candidates_shows_exactly_its_digits() {
+        let mut solution = grid( SOLUTION ).to_vec();
+        solution[0] = 0;
+        let mut candidates = vec![ 0; GRID_SIZE ];
+        candidates[0] = NUM_TO_BITMAP[2] | NUM_TO_BITMAP[5] | NUM_TO_BITMAP[9];
+        let mut previous = candidates.clone();
+        previous[0] |= NUM_TO_BITMAP[7];
+        let lines = piece_lines( &candidate_grid_pieces( &solution, &solution, &[false; GRID_SIZE], &candidates, Some( &previous ) ) );
+        // r1c1 takes three columns after the left border, a row of its block per line under the top one
+        let block: Vec<String> = ( 1..GRID_BLCK+1 ).map( |row| lines[row][ 3..3+GRID_BLCK ].iter().collect() ).collect();
+        assert_eq!( block, [ ".2.", ".5.", "x.9" ] );
+        // r1c2 is filled in, its digit in the middle row
+        let next: Vec<String> = ( 1..GRID_BLCK+1 ).map( |row| lines[row][ 7..7+GRID_BLCK ].iter().collect() ).collect();
+        assert_eq!( next, [ "   ", " 4 ", "   " ] );
+    }
+
+    #[test]
+    fn a_killer_export_draws_its_cages_and_sums() {
+        let markings = Markings { cages: cages(), ..Markings::default() };
+        let svg = svg( Variant::Killer, markings.clone() );
+        assert_eq!( svg.matches( "<g class=\"cage\"" ).count(), 1 );
+        // the top of the cage runs across both cells and its right side stops short of r1c3
+        assert!( svg.contains( "<line x1=\"4\" y1=\"4\" x2=\"40\" y2=\"4\"/>\n<line x1=\"4\" y1=\"36\" x2=\"40\" y2=\"36\"/>" ) );
+        assert!( svg.contains( "<line x1=\"40\" y1=\"4\" x2=\"76\" y2=\"4\"/>" ) );
+        assert!( svg.contains( "<line x1=\"76\" y1=\"4\" x2=\"76\" y2=\"36\"/>" ) );
+        assert!( svg.contains( ">10</text>" ) );
+        let text = text( Variant::Killer, markings, &UNICODE_BOX );
+        assert!( text.contains( " Cages:\n   a a . . . . . . .\n" ) );
+        assert!( text.contains( "   a=10\n" ) );
+    }
+
+    #[test]
+    fn a_cage_outline_turns_inside_the_corners_of_the_cage() {
+        // an L of three cells: r1c1, r2c1 and r2c2
+        let board = Board::of( Variant::Killer );
+        let lines = cage_outline( board, &[ 0, GRID_SQRT, GRID_SQRT + 1 ] );
+        assert_eq!( lines.len(), 8 );
+        // the right side of r1c1 runs down to the top of r2c2, which starts back under it
+        assert!( lines.contains( &( ( 0.9, 0.1 ), ( 0.9, 1.1 ) ) ) );
+        assert!( lines.contains( &( ( 0.9, 1.1 ), ( 1.9, 1.1 ) ) ) );
+        // the left side runs the height of both cells
+        assert!( lines.contains( &( ( 0.1, 0.1 ), ( 0.1, 1.0 ) ) ) && lines.contains( &( ( 0.1, 1.0 ), ( 0.1, 1.9 ) ) ) );
+    }
+
+    #[test]
+    fn a_jigsaw_export_draws_its_regions() {
+        let markings = Markings { regions: Some( regions() ), ..Markings::default() };
+        let svg = svg( Variant::Jigsaw, markings.clone() );
+        // the traded cells move the edge between the first two blocks
+        assert!( svg.contains( "<line class=\"region\" x1=\"80\" y1=\"0\" x2=\"80\" y2=\"40\"" ) );
+        assert!( svg.contains( "<line class=\"region\" x1=\"160\" y1=\"40\" x2=\"160\" y2=\"80\"" ) );
+        assert!( !svg.contains( "<line class=\"region\" x1=\"120\" y1=\"0\" x2=\"120\" y2=\"40\"" ) );
+        let unicode = text( Variant::Jigsaw, markings.clone(), &UNICODE_BOX );
+        assert!( unicode.starts_with( "Puzzle #1\n ┏━━━━━━━┳━━━━━━━━━━━━━━━┳━━━━━━━━━━━┓\n ┃ .   . ┃ .   .   .   . ┃" ) );
+        let ascii = text( Variant::Jigsaw, markings, &ASCII_BOX );
+        assert!( ascii.starts_with( "Puzzle #1\n +-------+---------------+-----------+\n | .   . | .   .   .   . |" ) );
+    }
+
+    #[test]
+    fn a_kropki_export_draws_its_dots() {
+        let links = relations::parse_links( "w:r2c1-r2c2 b:r2c2-r3c2", false ).unwrap();
+        let markings = Markings { links, ..Markings::default() };
+        let svg = svg( Variant::Kropki, markings.clone() );
+        assert!( svg.contains( "<circle class=\"dot\" cx=\"40\" cy=\"60\" r=\"5\" fill=\"white\"" ) );
+        assert!( svg.contains( "<circle class=\"dot\" cx=\"60\" cy=\"80\" r=\"5\" fill=\"black\"" ) );
+        let unicode = text( Variant::Kropki, markings.clone(), &UNICODE_BOX );
+        assert!( unicode.contains( " ○ " ) && unicode.contains( " ● " ) );
+        let ascii = text( Variant::Kropki, markings, &ASCII_BOX );
+        assert!( ascii.contains( " o " ) && ascii.contains( " * " ) );
+    }
+
+    #[test]
+    fn a_greater_than_export_draws_its_signs_pointing_at_the_smaller_digit() {
+        let links = relations::parse_links( ">:r2c1-r2c2", true ).unwrap();
+        let markings = Markings { links, ..Markings::default() };
+        let svg = svg( Variant::GreaterThan, markings.clone() );
+        assert!( svg.contains( "<polyline class=\"sign\" points=\"36.8,64.8 43.2,60 36.8,55.2\"" ) );
+        assert!( text( Variant::GreaterThan, markings, &UNICODE_BOX ).contains( " > " ) );
+    }
+
+    #[test]
+    fn an_even_odd_export_draws_its_parity_marks() {
+        let markings = Markings { masks: masks(), ..Markings::default() };
+        let svg = svg( Variant::EvenOdd, markings.clone() );
+        assert!( svg.contains( "<rect class=\"even\" x=\"6\" y=\"6\"" ) );
+        assert!( svg.contains( "<circle class=\"odd\" cx=\"60\" cy=\"20\"" ) );
+        assert!( text( Variant::EvenOdd, markings, &UNICODE_BOX ).contains( " Parity:\n   e o . . . . . . .\n" ) );
+    }
+
+    #[test]
+    fn a_text_export_fails_when_the_parity_marks_run_out_of_labels() {
+        let markings = Markings { masks: ( 1..GRID_SIZE+1 ).collect(), ..Markings::default() };
+        let items = [ item( markings ) ];
+        assert!( export_text( &items, Board::of( Variant::EvenOdd ), &constraints( Variant::EvenOdd ), &UNICODE_BOX ).is_err() );
+    }
+
+    #[test]
+    fn an_x_export_shades_its_diagonals() {
+        let svg = svg( Variant::X, Markings::default() );
+        assert_eq!( svg.matches( "class=\"shaded\"" ).count(), 2 * GRID_SQRT - 1 );
+        let text = text( Variant::X, Markings::default(), &UNICODE_BOX );
+        assert_eq!( text.matches( '[' ).count(), 2 * GRID_SQRT - 1 );
+    }
+
+    #[test]
+    fn an_export_draws_thermometers_arrows_and_sandwich_sums() {
+        let markings = Markings { clues: clues(), ..Markings::default() };
+        let svg = svg( Variant::Standard, markings.clone() );
+        assert!( svg.contains( "<circle class=\"thermometer\" cx=\"20\" cy=\"60\"" ) );
+        assert!( svg.contains( "points=\"20,60 60,60 60,100\"" ) );
+        assert!( svg.contains( "<circle class=\"arrow\" cx=\"180\" cy=\"180\"" ) );
+        assert!( svg.contains( "points=\"196,180 220,180 220,220\"" ) );
+        assert!( svg.contains( "<text class=\"sandwich\" x=\"376\" y=\"65\">12</text>" ) );
+        let text = text( Variant::Standard, markings, &UNICODE_BOX );
+        assert!( text.contains( " Thermometer from the bulb: r2c1 r2c2 r3c2\n" ) );
+        assert!( text.contains( " Arrow from the circle at r5c5: r5c6 r6c6\n" ) );
+        assert!( text.contains( " Sandwich in row 2: 12\n" ) );
+    }
+}
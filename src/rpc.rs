@@ -0,0 +1,151 @@
+use crate::server::{handle, Failure, Request};
+use crate::AppOptions;
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+
+// the operations the rpc mode answers, all shared with the http server
+const METHODS: [&str; 5] = ["solve", "validate", "candidates", "hint", "generate"];
+
+// json-rpc 2.0 error codes, the ones from -32000 down are ours
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const NO_SOLUTION: i64 = -32000;
+const OUT_OF_TIME: i64 = -32001;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Call {
+    jsonrpc: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    id: Option<Value>,
+    method: String,
+    params: Option<Value>,
+}
+
+// a field that is there, even as null; only a missing id makes a notification
+fn present<'de, D: Deserializer<'de>>( deserializer: D ) -> Result<Option<Value>, D::Error> {
+    Value::deserialize( deserializer ).map( Some )
+}
+
+fn error( id: Value, code: i64, message: String ) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+// the http statuses the shared operations fail with, as rpc error codes
+fn error_code( status: u16 ) -> i64 {
+    match status {
+        404 => METHOD_NOT_FOUND,
+        422 => NO_SOLUTION,
+        503 => OUT_OF_TIME,
+        _ => INVALID_PARAMS,
+    }
+}
+
+// answers one line of input; None for a notification, a call without an id
+fn respond( options: &AppOptions, line: &str ) -> Option<Value> {
+    let Call { jsonrpc, id, method, params } = match serde_json::from_str::<Value>( line ) {
+        Err( e ) => return Some( error( Value::Null, PARSE_ERROR, format!( "The request is not json: {}", e ) ) ),
+        Ok( value ) => match serde_json::from_value( value ) {
+            Ok( call ) => call,
+            Err( e ) => return Some( error( Value::Null, INVALID_REQUEST, format!( "The request is not valid: {}", e ) ) ),
+        },
+    };
+    let notification = id.is_none();
+    let id = id.unwrap_or( Value::Null );
+    if jsonrpc.as_deref().is_some_and( |version| version != "2.0" ) {
+        return Some( error( id, INVALID_REQUEST, "Only json-rpc 2.0 is spoken.".to_string() ) );
+    }
+    let result = if !METHODS.contains( &method.as_str() ) {
+        Err( ( 404, format!( "There is no method {}, the methods are {}.", method, METHODS.join( ", " ) ) ) )
+    } else {
+        serde_json::from_value::<Request>( params.unwrap_or( json!({}) ) )
+            .map_err( |e| -> Failure { ( 400, format!( "The params are not valid: {}", e ) ) } )
+            .and_then( |request| handle( options, &method, request ) )
+    };
+    if notification { return None; }
+    Some( match result {
+        Ok( result ) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err( (status, message) ) => error( id, error_code( status ), message ),
+    })
+}
+
+// reads one json-rpc request per line on stdin and writes one response per line
+// on stdout until stdin closes, so a single process can stay warm for a tool
+pub(crate) fn run( options: &AppOptions ) -> io::Result<i32> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut count = 0;
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+        if let Some( response ) = respond( options, &line ) {
+            let mut out = stdout.lock();
+            writeln!( out, "{}", response )?;
+            out.flush()?;
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::SOLUTION;
+    use crate::GRID_SQRT;
+
+    fn call( line: &str ) -> Option<Value> {
+        respond( &AppOptions { budget_ms: 5000, ..AppOptions::default() }, line )
+    }
+
+    // the solution with its first row left to fill in
+    fn puzzle() -> String {
+        format!( "{}{}", ".".repeat( GRID_SQRT ), &SOLUTION[ GRID_SQRT.. ] )
+    }
+
+    fn code( response: Option<Value> ) -> Value {
+        response.unwrap()["error"]["code"].clone()
+    }
+
+    #[test]
+    fn a_call_is_answered_with_its_id() {
+        let line = json!({ "jsonrpc": "2.0", "id": 7, "method": "solve", "params": { "puzzle": puzzle() } }).to_string();
+        assert_eq!( call( &line ), Some( json!({ "jsonrpc": "2.0", "id": 7, "result": { "solution": SOLUTION, "unique": true } }) ) );
+        let line = json!({ "id": "a", "method": "validate", "params": { "puzzle": puzzle() } }).to_string();
+        assert_eq!( call( &line ).unwrap()["result"]["valid"], true );
+        let line = json!({ "id": 1, "method": "candidates", "params": { "puzzle": puzzle() } }).to_string();
+        assert_eq!( call( &line ).unwrap()["result"]["candidates"][0], "6" );
+        let line = json!({ "id": 1, "method": "hint", "params": { "puzzle": puzzle() } }).to_string();
+        assert_eq!( call( &line ).unwrap()["result"]["hints"][0]["technique"], "hidden single" );
+        let line = json!({ "id": 1, "method": "generate" }).to_string();
+        assert!( call( &line ).unwrap()["result"]["puzzle"].is_string() );
+    }
+
+    #[test]
+    fn a_null_id_is_answered_but_a_missing_one_is_a_notification() {
+        let line = json!({ "jsonrpc": "2.0", "id": null, "method": "solve", "params": { "puzzle": puzzle() } }).to_string();
+        let response = call( &line ).unwrap();
+        assert_eq!( ( response["id"].clone(), response["result"]["unique"].clone() ), ( Value::Null, json!( true ) ) );
+        let line = json!({ "jsonrpc": "2.0", "method": "solve", "params": { "puzzle": puzzle() } }).to_string();
+        assert_eq!( call( &line ), None );
+        assert_eq!( call( r#"{"method":"shuffle"}"# ), None );
+    }
+
+    #[test]
+    fn bad_calls_get_their_error_codes() {
+        assert_eq!( code( call( "{" ) ), PARSE_ERROR );
+        assert_eq!( code( call( r#"{"id":1}"# ) ), INVALID_REQUEST );
+        assert_eq!( code( call( r#"{"id":1,"method":"solve","extra":1}"# ) ), INVALID_REQUEST );
+        assert_eq!( code( call( r#"{"jsonrpc":"1.0","id":1,"method":"solve"}"# ) ), INVALID_REQUEST );
+        assert_eq!( code( call( r#"{"id":1,"method":"count"}"# ) ), METHOD_NOT_FOUND );
+        assert_eq!( code( call( r#"{"id":1,"method":"solve","params":{"puzzel":""}}"# ) ), INVALID_PARAMS );
+        assert_eq!( code( call( r#"{"id":1,"method":"solve","params":{"puzzle":"123"}}"# ) ), INVALID_PARAMS );
+        let clash = json!({ "id": 1, "method": "solve", "params": { "puzzle": format!( "11{}", &puzzle()[2..] ) } }).to_string();
+        assert_eq!( code( call( &clash ) ), NO_SOLUTION );
+        let line = json!({ "id": 1, "method": "generate" }).to_string();
+        assert_eq!( code( respond( &AppOptions::default(), &line ) ), OUT_OF_TIME );
+    }
+}
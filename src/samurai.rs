@@ -0,0 +1,13 @@
+use crate::{GRID_BLCK, GRID_SQRT};
+
+// A samurai board is five grids on one square of cells, the centre grid sharing
+// a corner block with each of the others. The solver sees it as a board layout
+// like any other, see units::Board.
+pub const GRIDS: usize = 5;
+pub const BOARD_SQRT: usize = GRID_SQRT * 2 + GRID_BLCK;
+
+const FAR: usize = BOARD_SQRT - GRID_SQRT;
+const CENTRE: usize = FAR / 2;
+
+// the top left square of every grid as (row, column): top left, top right, centre, bottom left, bottom right
+pub const GRID_ORIGINS: [(usize, usize); GRIDS] = [ (0, 0), (0, FAR), (CENTRE, CENTRE), (FAR, 0), (FAR, FAR) ];
@@ -0,0 +1,367 @@
+use crate::hints::Hint;
+use crate::{AppOptions, Difficulty, Sudoku, Symmetry, Variant, GRID_SQRT, NUM_TO_BITMAP, NUM_TO_TEXT};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// requests larger than this are turned away before they are read
+const MAX_HEADER_BYTES: usize = 8 * 1024;
+const MAX_BODY_BYTES: usize = 16 * 1024;
+const READ_TIMEOUT_SECS: u64 = 5;
+const MAX_COUNT_LIMIT: usize = 10_000;
+
+// connections are answered by a fixed set of workers; past the ones waiting in
+// the queue a connection is turned away with a 503 instead of a new thread. One
+// waiting per worker keeps the wait to about one time budget.
+const WORKERS: usize = 8;
+const QUEUE_LENGTH: usize = WORKERS;
+
+// the json body of every endpoint, or the params of an rpc call; each one reads
+// the fields it needs
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Request {
+    puzzle: String,
+    variant: Option<String>,
+    side: Option<String>,
+    limit: Option<usize>,
+    size: Option<usize>,
+    difficulty: Option<String>,
+    symmetry: Option<String>,
+}
+
+// an error as the http status and the message sent back in the body
+pub(crate) type Failure = ( u16, String );
+
+fn bad_request( message: String ) -> Failure {
+    ( 400, message )
+}
+
+fn status_text( status: u16 ) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+fn respond( stream: &mut TcpStream, status: u16, body: &Value ) -> io::Result<()> {
+    let body = body.to_string();
+    write!( stream, "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, status_text( status ), body.len(), body )?;
+    stream.flush()
+}
+
+// reads the request line, the headers and the body, refusing anything over the size limits
+fn read_request( stream: &TcpStream ) -> Result<(String, String, Vec<u8>), Failure> {
+    let mut reader = BufReader::new( stream.take( ( MAX_HEADER_BYTES + MAX_BODY_BYTES ) as u64 ) );
+    let mut header_bytes = 0;
+    let mut read_line = |reader: &mut BufReader<_>| -> Result<String, Failure> {
+        let mut line = String::new();
+        reader.read_line( &mut line ).map_err( |_| bad_request( "The request could not be read.".to_string() ) )?;
+        header_bytes += line.len();
+        if header_bytes > MAX_HEADER_BYTES { return Err( ( 413, "The request headers are too large.".to_string() ) ); }
+        Ok( line.trim_end().to_string() )
+    };
+    let request_line = read_line( &mut reader )?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or( "" ).to_string();
+    let path = parts.next().unwrap_or( "" ).to_string();
+    let mut length = 0;
+    loop {
+        let line = read_line( &mut reader )?;
+        if line.is_empty() { break; }
+        if let Some( (name, value) ) = line.split_once( ':' ) {
+            if name.trim().eq_ignore_ascii_case( "content-length" ) {
+                length = value.trim().parse::<usize>().map_err( |_| bad_request( "The content length is not valid.".to_string() ) )?;
+            }
+        }
+    }
+    if length > MAX_BODY_BYTES {
+        return Err( ( 413, format!( "The request body is over {} bytes.", MAX_BODY_BYTES ) ) );
+    }
+    let mut body = vec![ 0; length ];
+    reader.read_exact( &mut body ).map_err( |_| bad_request( "The request body is shorter than its content length.".to_string() ) )?;
+    Ok( ( method, path, body ) )
+}
+
+fn hint_json( hint: &Hint ) -> Value {
+    json!({
+        "technique": hint.technique.to_string(),
+        "text": hint.to_string(),
+        "placement": hint.placement().map( |(pos, digit)| json!({ "cell": hint.cell_name( pos ), "digit": digit }) ),
+        "cells": hint.cells.iter().map( |&pos| hint.cell_name( pos ) ).collect::<Vec<String>>(),
+        "digits": hint.digits,
+        "units": hint.units.iter().map( |&u| hint.unit_name( u ) ).collect::<Vec<String>>(),
+        "eliminations": hint.eliminations.iter().map( |&(pos, digit)| json!({ "cell": hint.cell_name( pos ), "digit": digit }) ).collect::<Vec<Value>>(),
+    })
+}
+
+// a sudoku of the variant asked for, with its time budget up at `deadline`
+fn new_sudoku( options: &AppOptions, request: &Request, deadline: Instant ) -> Result<Sudoku, Failure> {
+    let variant = match &request.variant {
+        Some( name ) => name.parse::<Variant>().map_err( bad_request )?,
+        None => options.variant,
+    };
+    let mut sudoku = Sudoku::new( AppOptions { variant, debug: false, verbose: false, ..options.clone() } );
+    sudoku.deadline = Some( deadline );
+    Ok(sudoku)
+}
+
+fn load( sudoku: &mut Sudoku, request: &Request ) -> Result<(), Failure> {
+    sudoku.initialize_with_line( &request.puzzle, request.side.as_deref() ).map_err( bad_request )
+}
+
+fn out_of_time( sudoku: &Sudoku ) -> Failure {
+    ( 503, format!( "The request ran past its time budget of {} ms.", sudoku.app_options.budget_ms ) )
+}
+
+fn candidate_digits( bits: usize ) -> String {
+    ( 1..GRID_SQRT+1 ).filter( |&d| bits & NUM_TO_BITMAP[d] != 0 ).map( |d| NUM_TO_TEXT[d] ).collect()
+}
+
+// runs one of the operations, named without the leading slash, for the http
+// server and the rpc mode alike, with the time budget starting now
+pub(crate) fn handle( options: &AppOptions, method: &str, request: Request ) -> Result<Value, Failure> {
+    handle_by( options, method, request, Instant::now() + Duration::from_millis( options.budget_ms ) )
+}
+
+fn handle_by( options: &AppOptions, method: &str, request: Request, deadline: Instant ) -> Result<Value, Failure> {
+    let mut sudoku = new_sudoku( options, &request, deadline )?;
+    match method {
+        "solve" => {
+            load( &mut sudoku, &request )?;
+            sudoku.solve_fast( 1 );
+            if sudoku.timed_out { return Err( out_of_time( &sudoku ) ); }
+            if sudoku.solution_count != 1 { return Err( ( 422, "The puzzle has no solution.".to_string() ) ); }
+            let unique = sudoku.has_unique_solution();
+            if sudoku.timed_out { return Err( out_of_time( &sudoku ) ); }
+            Ok( json!({ "solution": sudoku.to_string(), "unique": unique }) )
+        },
+        "validate" => {
+            // a puzzle that does not load or clashes is reported, not refused
+            let problem = load( &mut sudoku, &request ).map_err( |(_, e)| e ).and_then( |_| sudoku.clash() ).err();
+            if let Some( reason ) = problem {
+                return Ok( json!({ "valid": false, "solvable": false, "unique": false, "reason": reason }) );
+            }
+            sudoku.solve_fast( 2 );
+            if sudoku.timed_out { return Err( out_of_time( &sudoku ) ); }
+            let ( solvable, unique ) = ( sudoku.solution_count > 0, sudoku.solution_count == 1 );
+            let reason = if !solvable { Some( "The puzzle has no solution." ) } else if !unique { Some( "The puzzle has more than one solution." ) } else { None };
+            Ok( json!({ "valid": unique, "solvable": solvable, "unique": unique, "reason": reason }) )
+        },
+        "candidates" => {
+            load( &mut sudoku, &request )?;
+            sudoku.clash().map_err( |e| ( 422, e ) )?;
+            sudoku.do_markup();
+            sudoku.prune_links();
+            let candidates = sudoku.candidates();
+            Ok( json!({ "candidates": candidates.iter().map( |&bits| candidate_digits( bits ) ).collect::<Vec<String>>() }) )
+        },
+        "count" => {
+            load( &mut sudoku, &request )?;
+            let limit = request.limit.unwrap_or( 2 ).clamp( 1, MAX_COUNT_LIMIT );
+            sudoku.solve_fast( limit );
+            // a count cut short by the budget is still a lower bound
+            Ok( json!({ "count": sudoku.solution_count, "limit": limit, "complete": !sudoku.timed_out }) )
+        },
+        "grade" => {
+            load( &mut sudoku, &request )?;
+            sudoku.solve_fast( 1 );
+            if sudoku.timed_out { return Err( out_of_time( &sudoku ) ); }
+            if sudoku.solution_count != 1 { return Err( ( 422, "The puzzle has no solution.".to_string() ) ); }
+            Ok( json!({ "difficulty": sudoku.grade().to_string().to_lowercase() }) )
+        },
+        "hint" => {
+            load( &mut sudoku, &request )?;
+            let hints = sudoku.hint().map_err( |e| ( 422, e ) )?;
+            Ok( json!({ "hints": hints.iter().map( hint_json ).collect::<Vec<Value>>() }) )
+        },
+        "generate" => {
+            if request.size.is_some_and( |size| size != GRID_SQRT ) {
+                return Err( bad_request( format!( "Only {}x{} grids can be generated.", GRID_SQRT, GRID_SQRT ) ) );
+            }
+            if let Some( difficulty ) = &request.difficulty {
+                sudoku.app_options.difficulty = Some( difficulty.parse::<Difficulty>().map_err( bad_request )? );
+            }
+            if let Some( symmetry ) = &request.symmetry {
+                sudoku.app_options.symmetry = symmetry.parse::<Symmetry>().map_err( bad_request )?;
+            }
+            if !sudoku.generate() { return Err( out_of_time( &sudoku ) ); }
+            // a puzzle that was made is unique, so it is graded and solved even
+            // when the budget ran out while it was being made
+            sudoku.deadline = None;
+            sudoku.timed_out = false;
+            let puzzle = sudoku.puzzle_line().map_err( |e| ( 500, e ) )?;
+            let side = sudoku.side_filename().map( |_| sudoku.side_line() );
+            let difficulty = sudoku.grade().to_string().to_lowercase();
+            sudoku.solve_fast( 1 );
+            Ok( json!({ "puzzle": puzzle, "side": side, "solution": sudoku.to_string(), "difficulty": difficulty }) )
+        },
+        _ => Err( ( 404, format!( "There is no {} operation.", method ) ) ),
+    }
+}
+
+fn serve_connection( mut stream: TcpStream, options: &AppOptions ) -> io::Result<()> {
+    stream.set_read_timeout( Some( Duration::from_secs( READ_TIMEOUT_SECS ) ) )?;
+    let result = read_request( &stream ).and_then( |(method, path, body)| {
+        if method != "POST" { return Err( ( 405, "Endpoints take a POST with a json body.".to_string() ) ); }
+        let request: Request = serde_json::from_slice( &body ).map_err( |e| bad_request( format!( "The request is not valid: {}", e ) ) )?;
+        match path.strip_prefix( '/' ) {
+            Some( method ) => handle( options, method, request ),
+            None => Err( ( 404, format!( "There is no endpoint {}.", path ) ) ),
+        }
+    });
+    match result {
+        Ok( body ) => respond( &mut stream, 200, &body ),
+        Err( (status, message) ) => respond( &mut stream, status, &json!({ "error": message }) ),
+    }
+}
+
+// answers requests on localhost until the process is stopped, each request
+// by one of the workers with its own sudoku
+pub(crate) fn serve( options: &AppOptions ) -> io::Result<i32> {
+    let listener = TcpListener::bind( ( "127.0.0.1", options.port ) )?;
+    println!( "Listening on http://127.0.0.1:{}, POST json to /solve, /validate, /candidates, /count, /grade, /hint or /generate.", options.port );
+    let ( queue, connections ) = mpsc::sync_channel::<TcpStream>( QUEUE_LENGTH );
+    let connections = Arc::new( Mutex::new( connections ) );
+    for _ in 0..WORKERS {
+        let connections = Arc::clone( &connections );
+        let options = options.clone();
+        thread::spawn( move || loop {
+            // the lock is only held while waiting for the next connection
+            let next = match connections.lock() {
+                Ok( connections ) => connections.recv(),
+                Err( _ ) => return,
+            };
+            match next {
+                Ok( stream ) => { serve_connection( stream, &options ).ok(); },
+                Err( _ ) => return,
+            }
+        });
+    }
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok( stream ) => stream,
+            Err( _ ) => continue,
+        };
+        if let Err( TrySendError::Full( mut stream ) | TrySendError::Disconnected( mut stream ) ) = queue.try_send( stream ) {
+            respond( &mut stream, 503, &json!({ "error": "The server is busy, try again shortly." }) ).ok();
+        }
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::SOLUTION;
+
+    // the budget is given to each call as its deadline, far off or already past
+    const NO_HURRY: Duration = Duration::from_secs( 3600 );
+
+    fn options() -> AppOptions {
+        AppOptions::default()
+    }
+
+    // the solution with its first row left to fill in
+    fn puzzle() -> String {
+        format!( "{}{}", ".".repeat( GRID_SQRT ), &SOLUTION[ GRID_SQRT.. ] )
+    }
+
+    fn request( puzzle: &str ) -> Request {
+        Request { puzzle: puzzle.to_string(), ..Request::default() }
+    }
+
+    fn call( method: &str, request: Request ) -> Result<Value, Failure> {
+        handle_by( &options(), method, request, Instant::now() + NO_HURRY )
+    }
+
+    #[test]
+    fn solve_returns_the_solution_and_whether_it_is_unique() {
+        assert_eq!( call( "solve", request( &puzzle() ) ).unwrap(), json!({ "solution": SOLUTION, "unique": true }) );
+        let empty = call( "solve", request( &".".repeat( GRID_SQRT * GRID_SQRT ) ) ).unwrap();
+        assert_eq!( empty["unique"], false );
+        assert_eq!( call( "solve", request( "123" ) ).unwrap_err().0, 400 );
+        let clash = format!( "11{}", &puzzle()[2..] );
+        assert_eq!( call( "solve", request( &clash ) ).unwrap_err().0, 422 );
+    }
+
+    #[test]
+    fn validate_reports_rather_than_refuses() {
+        assert_eq!( call( "validate", request( &puzzle() ) ).unwrap()["valid"], true );
+        let clash = call( "validate", request( &format!( "11{}", &puzzle()[2..] ) ) ).unwrap();
+        assert_eq!( ( clash["valid"].clone(), clash["solvable"].clone() ), ( json!( false ), json!( false ) ) );
+        assert!( clash["reason"].as_str().unwrap().contains( "both hold 1" ) );
+        let open = call( "validate", request( &".".repeat( GRID_SQRT * GRID_SQRT ) ) ).unwrap();
+        assert_eq!( open["reason"], "The puzzle has more than one solution." );
+        assert!( call( "validate", request( "123" ) ).unwrap()["reason"].is_string() );
+    }
+
+    #[test]
+    fn candidates_count_and_grade_read_the_puzzle() {
+        let candidates = call( "candidates", request( &puzzle() ) ).unwrap();
+        assert_eq!( candidates["candidates"][0], "6" );
+        assert_eq!( candidates["candidates"][ GRID_SQRT ], "" );
+
+        let count = call( "count", Request { limit: Some( 3 ), ..request( &".".repeat( GRID_SQRT * GRID_SQRT ) ) } ).unwrap();
+        assert_eq!( count, json!({ "count": 3, "limit": 3, "complete": true }) );
+        assert_eq!( call( "count", Request { limit: Some( 0 ), ..request( &puzzle() ) } ).unwrap()["limit"], 1 );
+
+        assert_eq!( call( "grade", request( &puzzle() ) ).unwrap(), json!({ "difficulty": "easy" }) );
+    }
+
+    #[test]
+    fn hint_lists_the_steps_to_the_next_digit() {
+        let hints = call( "hint", request( &puzzle() ) ).unwrap();
+        let hint = &hints["hints"][0];
+        assert_eq!( hint["technique"], "hidden single" );
+        assert_eq!( hint["placement"], json!({ "cell": "r1c7", "digit": 1 }) );
+        assert_eq!( hint["units"], json!([ "row 1" ]) );
+        assert_eq!( call( "hint", request( SOLUTION ) ).unwrap(), json!({ "hints": [] }) );
+    }
+
+    #[test]
+    fn generate_makes_a_puzzle_with_its_solution() {
+        let generated = call( "generate", Request { symmetry: Some( "rotational".to_string() ), ..Request::default() } ).unwrap();
+        let solved = call( "solve", request( generated["puzzle"].as_str().unwrap() ) ).unwrap();
+        assert_eq!( solved, json!({ "solution": generated["solution"], "unique": true }) );
+        assert_eq!( call( "generate", Request { size: Some( 4 ), ..Request::default() } ).unwrap_err().0, 400 );
+        assert_eq!( call( "generate", Request { difficulty: Some( "silly".to_string() ), ..Request::default() } ).unwrap_err().0, 400 );
+        assert_eq!( handle_by( &options(), "generate", Request::default(), Instant::now() - NO_HURRY ).unwrap_err().0, 503 );
+    }
+
+    #[test]
+    fn generate_makes_a_jigsaw_with_its_regions() {
+        let generated = call( "generate", Request { variant: Some( "jigsaw".to_string() ), ..Request::default() } ).unwrap();
+        let puzzle = generated["puzzle"].as_str().unwrap();
+        assert_eq!( puzzle.split_whitespace().count(), 2 );
+        let solved = call( "solve", Request { variant: Some( "jigsaw".to_string() ), ..request( puzzle ) } ).unwrap();
+        assert_eq!( solved, json!({ "solution": generated["solution"], "unique": true }) );
+    }
+
+    #[test]
+    fn a_count_past_its_deadline_is_cut_short() {
+        // the clock is read every so many nodes, so only a long search notices
+        let open = Request { limit: Some( MAX_COUNT_LIMIT ), ..request( &".".repeat( GRID_SQRT * GRID_SQRT ) ) };
+        let count = handle_by( &options(), "count", open, Instant::now() - NO_HURRY ).unwrap();
+        assert_eq!( count["complete"], false );
+        assert!( count["count"].as_u64().unwrap() < MAX_COUNT_LIMIT as u64 );
+    }
+
+    #[test]
+    fn unknown_methods_and_variants_are_refused() {
+        assert_eq!( call( "shuffle", request( &puzzle() ) ).unwrap_err().0, 404 );
+        assert_eq!( call( "solve", Request { variant: Some( "chess".to_string() ), ..request( &puzzle() ) } ).unwrap_err().0, 400 );
+    }
+}
@@ -0,0 +1,319 @@
+use crate::render::GridPiece;
+use crate::units::Board;
+use crate::{Sudoku, Variant, GRID_SQRT, NUM_TO_TEXT};
+use console::{style, Key, Term};
+use std::fmt;
+use std::io::{self, Write};
+
+// one node of the search: a digit tried in a cell, the digit taken back out
+// again, or a full grid reached; `depth` counts the digits placed by the search
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Step {
+    Try { pos: usize, value: usize, depth: usize },
+    Backtrack { pos: usize, value: usize, depth: usize },
+    Solution { depth: usize },
+}
+
+// the search for one puzzle as read back from a trace file: the puzzle line and
+// any side line it was read with, the grid the recursive search started from and
+// every step it took
+pub struct Trace {
+    pub number: usize,
+    pub line: String,
+    pub side: Option<String>,
+    pub start: Vec<usize>,
+    pub steps: Vec<Step>,
+}
+
+// A trace file starts with the variant, then for every puzzle:
+//
+//   puzzle <number> <puzzle line>
+//   side <dots or signs line>          (kropki and greater-than only)
+//   start <the grid after hidden singles>
+//   t <cell> <digit> <depth>           a digit tried
+//   b <cell> <digit> <depth>           a digit taken back
+//   s <depth>                          a solution
+//
+// with cells numbered from 0 in reading order over the board.
+pub const TRACE_HEADER: &str = "# sudoku search trace";
+
+impl Trace {
+
+    // the trace of a puzzle on a board of `size` cells
+    pub fn new( number: usize, line: String, side: Option<String>, size: usize ) -> Trace {
+        Trace { number, line, side, start: vec![ 0; size ], steps: Vec::new() }
+    }
+}
+
+// Writes the search of every puzzle solved to a trace file as it goes, so a long
+// search is never held in memory. The search cannot stop for a failed write, so
+// the first one is kept and `finish` returns it once the puzzle is done.
+pub struct Recorder<W: Write> {
+    writer: W,
+    depth: usize,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Recorder<W> {
+
+    pub(crate) fn new( mut writer: W, variant: Variant ) -> io::Result<Recorder<W>> {
+        writeln!( writer, "{}", TRACE_HEADER )?;
+        writeln!( writer, "variant {}", variant )?;
+        Ok( Recorder { writer, depth: 0, error: None } )
+    }
+
+    fn write_line( &mut self, line: fmt::Arguments ) {
+        if self.error.is_some() { return; }
+        if let Err( e ) = self.writer.write_fmt( line ).and_then( |_| self.writer.write_all( b"\n" ) ) {
+            self.error = Some( e );
+        }
+    }
+
+    pub fn puzzle( &mut self, number: usize, line: &str, side: Option<&str> ) {
+        self.depth = 0;
+        self.write_line( format_args!( "puzzle {} {}", number, line ) );
+        if let Some( side ) = side { self.write_line( format_args!( "side {}", side ) ); }
+    }
+
+    pub fn start( &mut self, grid: &[usize] ) {
+        let grid = grid.iter().map( |&value| NUM_TO_TEXT[ value ] ).collect::<String>();
+        self.write_line( format_args!( "start {}", grid ) );
+    }
+
+    pub fn tried( &mut self, pos: usize, value: usize ) {
+        self.depth += 1;
+        let depth = self.depth;
+        self.write_line( format_args!( "t {} {} {}", pos, value, depth ) );
+    }
+
+    pub fn backtracked( &mut self, pos: usize, value: usize ) {
+        let depth = self.depth;
+        self.write_line( format_args!( "b {} {} {}", pos, value, depth ) );
+        self.depth -= 1;
+    }
+
+    pub fn solved( &mut self ) {
+        let depth = self.depth;
+        self.write_line( format_args!( "s {}", depth ) );
+    }
+
+    // the first write that failed during the last puzzle
+    pub fn finish( &mut self ) -> io::Result<()> {
+        match self.error.take() {
+            Some( e ) => Err( e ),
+            None => Ok(()),
+        }
+    }
+
+    pub fn flush( &mut self ) -> io::Result<()> {
+        self.finish()?;
+        self.writer.flush()
+    }
+}
+
+fn parse_step( kind: &str, fields: &[&str], size: usize ) -> Option<Step> {
+    let numbers: Vec<usize> = fields.iter().map( |field| field.parse::<usize>().ok() ).collect::<Option<Vec<usize>>>()?;
+    let valid = |pos: usize, value: usize| pos < size && ( 1..=GRID_SQRT ).contains( &value );
+    match ( kind, numbers.as_slice() ) {
+        ( "t", &[pos, value, depth] ) if valid( pos, value ) => Some( Step::Try { pos, value, depth } ),
+        ( "b", &[pos, value, depth] ) if valid( pos, value ) => Some( Step::Backtrack { pos, value, depth } ),
+        ( "s", &[depth] ) => Some( Step::Solution { depth } ),
+        _ => None,
+    }
+}
+
+// reads a trace file into the variant it was made with and its puzzles, the
+// variant coming before the puzzles since it sets the cells of the board
+pub(crate) fn parse_traces( text: &str ) -> Result<(Variant, Vec<Trace>), String> {
+    let mut variant = None;
+    let mut traces: Vec<Trace> = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let invalid = || format!( "Line {} of the trace is not valid.", n+1 );
+        let size = variant.map( |variant| Board::of( variant ).size ).unwrap_or( 0 );
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [] => {},
+            [first, ..] if first.starts_with( '#' ) => {},
+            ["variant", name] => variant = Some( name.parse::<Variant>()? ),
+            ["puzzle", number, ..] if variant.is_some() => {
+                let number = number.parse::<usize>().map_err( |_| invalid() )?;
+                let rest = line.split_whitespace().skip( 2 ).collect::<Vec<&str>>().join( " " );
+                traces.push( Trace::new( number, rest, None, size ) );
+            },
+            ["side", ..] => {
+                let trace = traces.last_mut().ok_or_else( invalid )?;
+                trace.side = Some( line.split_whitespace().skip( 1 ).collect::<Vec<&str>>().join( " " ) );
+            },
+            ["start", grid] if grid.len() == size => {
+                let trace = traces.last_mut().ok_or_else( invalid )?;
+                for (pos, c) in grid.chars().enumerate() {
+                    trace.start[pos] = NUM_TO_TEXT[ ..GRID_SQRT+1 ].iter().position( |&t| t == c ).ok_or_else( invalid )?;
+                }
+            },
+            [kind, rest @ ..] => {
+                let step = parse_step( kind, rest, size ).ok_or_else( invalid )?;
+                traces.last_mut().ok_or_else( invalid )?.steps.push( step );
+            },
+        }
+    }
+    Ok( ( variant.ok_or( "The trace does not say which variant it is for." )?, traces ) )
+}
+
+// the grid after the first `count` steps of a trace
+pub fn grid_at( trace: &Trace, count: usize ) -> Vec<usize> {
+    let mut grid = trace.start.clone();
+    for step in trace.steps[ ..count ].iter() {
+        match *step {
+            Step::Try { pos, value, .. } => grid[pos] = value,
+            Step::Backtrack { pos, .. } => grid[pos] = 0,
+            Step::Solution { .. } => {},
+        }
+    }
+    grid
+}
+
+fn describe( board: &Board, step: Option<&Step> ) -> String {
+    match step {
+        None => "start: the givens and the hidden singles found before searching".to_string(),
+        Some( Step::Try { pos, value, depth } ) => format!( "depth {}  try {} = {}", depth, board.cell_name( *pos ), value ),
+        Some( Step::Backtrack { pos, value, depth } ) => format!( "depth {}  backtrack {}, {} failed", depth, board.cell_name( *pos ), value ),
+        Some( Step::Solution { depth } ) => format!( "depth {}  solution found", depth ),
+    }
+}
+
+fn draw( term: &Term, board: &Board, trace: &Trace, count: usize, pieces: Vec<GridPiece> ) {
+    term.clear_screen().ok();
+    println!( "{}  {}", style( format!( "Trace of puzzle #{}", trace.number ) ).white(),
+        style( format!( "step {} of {}", count, trace.steps.len() ) ).green() );
+    let last = if count > 0 { Some( &trace.steps[ count-1 ] ) } else { None };
+    let ( marked, backtrack ) = match last {
+        Some( Step::Try { pos, .. } ) => ( Some( *pos ), false ),
+        Some( Step::Backtrack { pos, .. } ) => ( Some( *pos ), true ),
+        _ => ( None, false ),
+    };
+    let mut pos = 0;
+    for piece in pieces {
+        match piece {
+            GridPiece::Border( s ) => print!( "{}", style( s ).green() ),
+            GridPiece::Cell { value, given, .. } => {
+                let text = style( format!( " {} ", NUM_TO_TEXT[ value ] ) );
+                let text = if given { text.yellow().bright() } else if trace.start[pos] != 0 { text.yellow() } else { text.white() };
+                match marked {
+                    Some( m ) if m == pos && backtrack => print!( "{}", text.red().reverse() ),
+                    Some( m ) if m == pos => print!( "{}", text.green().reverse() ),
+                    _ => print!( "{}", text ),
+                }
+                pos += 1;
+            },
+            GridPiece::Candidate { .. } => {},
+            GridPiece::EndOfLine => println!(),
+        }
+    }
+    println!();
+    println!( " {}", style( describe( board, last ) ).cyan() );
+    println!( " {}", style( "right/l next  left/h back  ]/[ 100 steps  home/end  down/j up/k puzzle  q quit" ).white() );
+}
+
+// steps through the traces forwards and backwards in the terminal
+pub(crate) fn replay( sudoku: &mut Sudoku, traces: &[Trace] ) -> io::Result<()> {
+    let term = Term::stdout();
+    let mut t = 0;
+    let mut loaded = None;
+    let mut count = 0;
+    loop {
+        let trace = &traces[t];
+        if loaded != Some( t ) {
+            sudoku.initialize_with_line( &trace.line, trace.side.as_deref() )
+                .map_err( |e| io::Error::new( io::ErrorKind::InvalidData, format!( "Puzzle #{} in the trace is not valid: {}", trace.number, e ) ) )?;
+            loaded = Some( t );
+        }
+        let grid = grid_at( trace, count );
+        draw( &term, sudoku.board(), trace, count, sudoku.grid_pieces_for( &sudoku.puzzle, &grid ) );
+        let len = trace.steps.len();
+        match term.read_key()? {
+            Key::ArrowRight | Key::Char('l') | Key::Char(' ') => count = ( count + 1 ).min( len ),
+            Key::ArrowLeft | Key::Char('h') => count = count.saturating_sub( 1 ),
+            Key::Char(']') => count = ( count + 100 ).min( len ),
+            Key::Char('[') => count = count.saturating_sub( 100 ),
+            Key::Home => count = 0,
+            Key::End => count = len,
+            Key::ArrowDown | Key::Char('j') if t+1 < traces.len() => { t += 1; count = 0; },
+            Key::ArrowUp | Key::Char('k') if t > 0 => { t -= 1; count = 0; },
+            Key::Char('q') | Key::Escape => return Ok(()),
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{grid, SOLUTION};
+    use crate::GRID_SIZE;
+
+    // a recorded search of the solution with two cells taken out
+    fn recorded() -> String {
+        let mut start = grid( SOLUTION );
+        start[0] = 0;
+        start[1] = 0;
+        let mut recorder = Recorder::new( Vec::new(), Variant::Standard ).unwrap();
+        recorder.puzzle( 3, "..7529138285413769193867452758134296321698574469752381816275943534981627972346815", None );
+        recorder.start( &start );
+        recorder.tried( 0, 4 );
+        recorder.backtracked( 0, 4 );
+        recorder.tried( 0, 6 );
+        recorder.tried( 1, 4 );
+        recorder.solved();
+        recorder.flush().unwrap();
+        String::from_utf8( recorder.writer ).unwrap()
+    }
+
+    fn error( text: &str ) -> String {
+        parse_traces( text ).err().unwrap()
+    }
+
+    #[test]
+    fn a_recorded_search_reads_back() {
+        let ( variant, traces ) = parse_traces( &recorded() ).unwrap();
+        assert_eq!( variant, Variant::Standard );
+        assert_eq!( traces.len(), 1 );
+        let trace = &traces[0];
+        assert_eq!( ( trace.number, trace.side.clone() ), ( 3, None ) );
+        assert_eq!( trace.steps, vec![
+            Step::Try { pos: 0, value: 4, depth: 1 },
+            Step::Backtrack { pos: 0, value: 4, depth: 1 },
+            Step::Try { pos: 0, value: 6, depth: 1 },
+            Step::Try { pos: 1, value: 4, depth: 2 },
+            Step::Solution { depth: 2 },
+        ] );
+        assert_eq!( trace.start[ 2.. ], grid( SOLUTION )[ 2.. ] );
+        assert_eq!( grid_at( trace, 2 )[0], 0 );
+        assert_eq!( grid_at( trace, trace.steps.len() ), grid( SOLUTION ).to_vec() );
+    }
+
+    #[test]
+    fn steps_and_starts_outside_the_board_are_rejected() {
+        let text = recorded();
+        let line = |n: usize| text.lines().position( |line| line.starts_with( [ 't', 'b' ] ) ).unwrap() + n + 1;
+        let replaced = |from: &str, to: &str| text.replacen( from, to, 1 );
+        assert_eq!( error( &replaced( "t 0 4 1", "t 0 10 1" ) ), format!( "Line {} of the trace is not valid.", line( 0 ) ) );
+        assert_eq!( error( &replaced( "b 0 4 1", "b 0 0 1" ) ), format!( "Line {} of the trace is not valid.", line( 1 ) ) );
+        assert!( parse_traces( &replaced( "t 1 4 2", &format!( "t {} 4 2", GRID_SIZE ) ) ).is_err() );
+        assert!( parse_traces( &replaced( "s 2", "s two" ) ).is_err() );
+        assert!( parse_traces( &replaced( "start ..7", "start A.7" ) ).is_err() );
+        assert!( parse_traces( &replaced( "start ..7", "start .7" ) ).is_err() );
+    }
+
+    #[test]
+    fn the_variant_comes_first_and_sizes_the_board() {
+        let text = recorded();
+        assert_eq!( error( &text.replacen( "variant standard", "", 1 ) ), "Line 3 of the trace is not valid." );
+        assert!( error( &text.replacen( "variant standard", "variant chess", 1 ) ).contains( "chess" ) );
+        assert_eq!( error( TRACE_HEADER ), "The trace does not say which variant it is for." );
+        assert_eq!( error( "variant standard\nt 0 4 1" ), "Line 2 of the trace is not valid." );
+        // a samurai start covers all five grids
+        assert!( parse_traces( &text.replacen( "variant standard", "variant samurai", 1 ) ).is_err() );
+        let samurai = format!( "variant samurai\npuzzle 1 x\nstart {}\nt 368 9 1\n", ".".repeat( Board::of( Variant::Samurai ).size ) );
+        assert_eq!( parse_traces( &samurai ).unwrap().1[0].steps, vec![ Step::Try { pos: 368, value: 9, depth: 1 } ] );
+    }
+}
@@ -0,0 +1,326 @@
+use crate::samurai;
+use crate::{Variant, GRID_BLCK, GRID_SQRT, GRID_SIZE};
+use std::sync::OnceLock;
+
+// the cells of the asterisk unit, which only exists on a 9x9 grid
+const ASTERISK: [usize; 9] = [ 13, 20, 24, 37, 40, 43, 56, 60, 67 ];
+const _: () = assert!( GRID_SIZE == 81, "the asterisk unit is laid out for a 9x9 grid" );
+
+const KNIGHT_MOVES: [(isize, isize); 8] = [ (-2,-1), (-2,1), (-1,-2), (-1,2), (1,-2), (1,2), (2,-1), (2,1) ];
+const KING_MOVES: [(isize, isize); 8] = [ (-1,-1), (-1,0), (-1,1), (0,-1), (0,1), (1,-1), (1,0), (1,1) ];
+
+// one grid of a board: its top left square and its cells in reading order
+pub struct Grid {
+    pub top: usize,
+    pub left: usize,
+    pub cells: [usize; GRID_SIZE],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum UnitKind {
+    Row,
+    Column,
+    Block,
+}
+
+// The square the cells of a puzzle sit on: a single grid, or the five grids of a
+// samurai sharing their corner blocks. Cells are numbered in reading order over
+// the squares some grid covers, so on a single grid a cell is its position.
+pub struct Board {
+    pub side: usize,
+    pub size: usize,
+    pub grids: Vec<Grid>,
+    // the rows, columns and blocks of every grid as (grid, kind, number), a
+    // block shared by two grids only once
+    units: Vec<(usize, UnitKind, usize)>,
+    cell_at: Vec<Option<usize>>,
+    coords: Vec<(usize, usize)>,
+}
+
+// the position in a grid of the top left cell of block `n`
+fn block_corner( n: usize ) -> usize {
+    ( n / GRID_BLCK ) * GRID_BLCK * GRID_SQRT + ( n % GRID_BLCK ) * GRID_BLCK
+}
+
+impl Board {
+
+    fn new( side: usize, origins: &[(usize, usize)] ) -> Board {
+        let square = |top: usize, left: usize, pos: usize| ( top + pos / GRID_SQRT ) * side + left + pos % GRID_SQRT;
+        let mut covered = vec![ false; side * side ];
+        for &(top, left) in origins.iter() {
+            for pos in 0..GRID_SIZE { covered[ square( top, left, pos ) ] = true; }
+        }
+        let mut cell_at = vec![ None; side * side ];
+        let mut coords = Vec::new();
+        for (s, &covered) in covered.iter().enumerate() {
+            if covered {
+                cell_at[s] = Some( coords.len() );
+                coords.push( ( s / side, s % side ) );
+            }
+        }
+        let grids: Vec<Grid> = origins.iter().map( |&(top, left)| {
+            let mut cells = [0; GRID_SIZE];
+            for (pos, cell) in cells.iter_mut().enumerate() {
+                *cell = cell_at[ square( top, left, pos ) ].unwrap_or( 0 );
+            }
+            Grid { top, left, cells }
+        }).collect();
+
+        let mut units = Vec::new();
+        for (g, grid) in grids.iter().enumerate() {
+            units.extend( ( 0..GRID_SQRT ).map( |n| ( g, UnitKind::Row, n ) ) );
+            units.extend( ( 0..GRID_SQRT ).map( |n| ( g, UnitKind::Column, n ) ) );
+            for n in 0..GRID_SQRT {
+                let corner = grid.cells[ block_corner( n ) ];
+                let shared = units.iter().any( |&(h, kind, m): &(usize, UnitKind, usize)| kind == UnitKind::Block && grids[h].cells[ block_corner( m ) ] == corner );
+                if !shared { units.push( ( g, UnitKind::Block, n ) ); }
+            }
+        }
+        Board { side, size: coords.len(), grids, units, cell_at, coords }
+    }
+
+    // the board a variant is played on, built once
+    pub fn of( variant: Variant ) -> &'static Board {
+        static STANDARD: OnceLock<Board> = OnceLock::new();
+        static SAMURAI: OnceLock<Board> = OnceLock::new();
+        match variant {
+            Variant::Samurai => SAMURAI.get_or_init( || Board::new( samurai::BOARD_SQRT, &samurai::GRID_ORIGINS ) ),
+            _ => STANDARD.get_or_init( || Board::new( GRID_SQRT, &[ (0, 0) ] ) ),
+        }
+    }
+
+    // the cell at a row and column of the board, None outside every grid
+    pub fn cell_at( &self, row: usize, col: usize ) -> Option<usize> {
+        if row >= self.side || col >= self.side { return None; }
+        self.cell_at[ row * self.side + col ]
+    }
+
+    pub fn coords( &self, cell: usize ) -> (usize, usize) {
+        self.coords[ cell ]
+    }
+
+    pub fn cell_name( &self, cell: usize ) -> String {
+        let (row, col) = self.coords( cell );
+        format!( "r{}c{}", row + 1, col + 1 )
+    }
+
+    // the values of the cells of grid `g`, in the grid's reading order
+    pub fn grid_values( &self, values: &[usize], g: usize ) -> [usize; GRID_SIZE] {
+        let mut grid = [0; GRID_SIZE];
+        for (pos, value) in grid.iter_mut().enumerate() {
+            *value = values[ self.grids[g].cells[pos] ];
+        }
+        grid
+    }
+}
+
+// units are the rows, columns and boxes or jigsaw regions of each grid, then the
+// extra units of a variant
+pub fn unit_name( variant: Variant, unit: usize ) -> String {
+    let board = Board::of( variant );
+    match board.units.get( unit ) {
+        Some( &(g, kind, n) ) => {
+            let kind = match kind {
+                UnitKind::Row => "row",
+                UnitKind::Column => "column",
+                UnitKind::Block if variant == Variant::Jigsaw => "region",
+                UnitKind::Block => "box",
+            };
+            if board.grids.len() > 1 { format!( "{} {} of grid {}", kind, n + 1, g + 1 ) } else { format!( "{} {}", kind, n + 1 ) }
+        },
+        None => format!( "extra unit {}", unit - board.units.len() + 1 ),
+    }
+}
+
+// The constraints of a board as a list of units, groups of GRID_SQRT cells that
+// hold every digit exactly once, plus the cells each cell must differ from.
+// A unit based variant only declares its extra units in `extra_units`, a variant
+// of pairwise constraints between cells declares them in `extra_peers`.
+pub struct Constraints {
+    pub units: Vec<Vec<usize>>,
+    pub peers: Vec<Vec<usize>>,
+    first_extra_unit: usize,
+}
+
+impl Constraints {
+
+    // `regions` are the blocks of every grid, or a jigsaw's regions; `groups` are
+    // extra all-different groups that are not complete units, like killer cages
+    pub fn new( variant: Variant, regions: &[usize; GRID_SIZE], groups: &[Vec<usize>] ) -> Constraints {
+        let board = Board::of( variant );
+        let mut units: Vec<Vec<usize>> = Vec::new();
+        for &(g, kind, n) in board.units.iter() {
+            let cells = &board.grids[g].cells;
+            units.push( match kind {
+                UnitKind::Row => ( 0..GRID_SQRT ).map( |i| cells[ n * GRID_SQRT + i ] ).collect(),
+                UnitKind::Column => ( 0..GRID_SQRT ).map( |i| cells[ i * GRID_SQRT + n ] ).collect(),
+                UnitKind::Block => ( 0..GRID_SIZE ).filter( |&pos| regions[pos] == n ).map( |pos| cells[pos] ).collect(),
+            });
+        }
+        let first_extra_unit = units.len();
+        units.extend( extra_units( variant ) );
+
+        let mut peers = vec![ Vec::new(); board.size ];
+        for group in units.iter().chain( groups.iter() ) {
+            for &pos in group.iter() {
+                peers[pos].extend( group.iter().filter( |&&other| other != pos ) );
+            }
+        }
+        for (pos, cells) in peers.iter_mut().enumerate() {
+            cells.extend( extra_peers( variant, pos ) );
+            cells.sort_unstable();
+            cells.dedup();
+        }
+        Constraints { units, peers, first_extra_unit }
+    }
+
+    // the cells of the units a variant adds to a standard grid
+    pub fn shaded_cells( &self ) -> Vec<bool> {
+        let mut shaded = vec![ false; self.peers.len() ];
+        for unit in self.units[ self.first_extra_unit.. ].iter() {
+            for &pos in unit.iter() { shaded[pos] = true; }
+        }
+        shaded
+    }
+}
+
+fn extra_units( variant: Variant ) -> Vec<Vec<usize>> {
+    match variant {
+        Variant::X => vec![
+            ( 0..GRID_SQRT ).map( |n| n * GRID_SQRT + n ).collect(),
+            ( 0..GRID_SQRT ).map( |n| n * GRID_SQRT + GRID_SQRT-1-n ).collect(),
+        ],
+        // the windows sit one cell in from the blocks, separated by a single row or column
+        Variant::Hyper => {
+            let mut units = Vec::new();
+            for wy in 0..GRID_BLCK-1 {
+                for wx in 0..GRID_BLCK-1 {
+                    let tl = ( 1 + wy * (GRID_BLCK+1) ) * GRID_SQRT + 1 + wx * (GRID_BLCK+1);
+                    units.push( ( 0..GRID_SQRT ).map( |n| tl + ( n / GRID_BLCK ) * GRID_SQRT + n % GRID_BLCK ).collect() );
+                }
+            }
+            units
+        },
+        // the centre cell of every block
+        Variant::CentreDot => vec![
+            ( 0..GRID_SQRT ).map( |b| ( ( b / GRID_BLCK ) * GRID_BLCK + GRID_BLCK / 2 ) * GRID_SQRT + ( b % GRID_BLCK ) * GRID_BLCK + GRID_BLCK / 2 ).collect(),
+        ],
+        Variant::Asterisk => vec![ ASTERISK.to_vec() ],
+        _ => Vec::new(),
+    }
+}
+
+// the cells a chess piece could move to from `pos`
+fn moves( pos: usize, offsets: &[(isize, isize)] ) -> Vec<usize> {
+    let size = GRID_SQRT as isize;
+    let y = ( pos / GRID_SQRT ) as isize;
+    let x = ( pos % GRID_SQRT ) as isize;
+    offsets.iter()
+        .map( |&(dy, dx)| ( y + dy, x + dx ) )
+        .filter( |&(ny, nx)| ny >= 0 && nx >= 0 && ny < size && nx < size )
+        .map( |(ny, nx)| ( ny * size + nx ) as usize )
+        .collect()
+}
+
+fn extra_peers( variant: Variant, pos: usize ) -> Vec<usize> {
+    match variant {
+        Variant::AntiKnight => moves( pos, &KNIGHT_MOVES ),
+        Variant::AntiKing => moves( pos, &KING_MOVES ),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jigsaw::standard_regions;
+
+    fn constraints( variant: Variant ) -> Constraints {
+        Constraints::new( variant, &standard_regions(), &[] )
+    }
+
+    #[test]
+    fn standard_units_are_rows_columns_and_boxes() {
+        let c = constraints( Variant::Standard );
+        assert_eq!( c.units.len(), 3 * GRID_SQRT );
+        assert!( c.units.iter().all( |unit| unit.len() == GRID_SQRT ) );
+        assert_eq!( c.units[1], ( 9..18 ).collect::<Vec<usize>>() );
+        assert_eq!( c.units[ GRID_SQRT + 1 ], vec![ 1, 10, 19, 28, 37, 46, 55, 64, 73 ] );
+        assert_eq!( c.units[ 2 * GRID_SQRT + 4 ], vec![ 30, 31, 32, 39, 40, 41, 48, 49, 50 ] );
+        assert!( c.peers.iter().all( |peers| peers.len() == 20 ) );
+        assert!( c.shaded_cells().iter().all( |&shaded| !shaded ) );
+        assert_eq!( unit_name( Variant::Standard, 2 * GRID_SQRT ), "box 1" );
+        assert_eq!( unit_name( Variant::Jigsaw, 2 * GRID_SQRT ), "region 1" );
+        assert_eq!( unit_name( Variant::X, 3 * GRID_SQRT + 1 ), "extra unit 2" );
+    }
+
+    #[test]
+    fn every_cell_is_in_each_extra_unit_once() {
+        for &(variant, count) in &[ (Variant::X, 2), (Variant::Hyper, 4), (Variant::CentreDot, 1), (Variant::Asterisk, 1) ] {
+            let c = constraints( variant );
+            assert_eq!( c.units.len(), 3 * GRID_SQRT + count );
+            for unit in c.units[ 3 * GRID_SQRT.. ].iter() {
+                let mut cells = unit.clone();
+                cells.sort_unstable();
+                cells.dedup();
+                assert_eq!( cells.len(), GRID_SQRT );
+            }
+        }
+    }
+
+    #[test]
+    fn extra_units_add_peers_and_shading() {
+        let x = constraints( Variant::X );
+        assert_eq!( x.units[ 3 * GRID_SQRT ], vec![ 0, 10, 20, 30, 40, 50, 60, 70, 80 ] );
+        assert_eq!( x.units[ 3 * GRID_SQRT + 1 ], vec![ 8, 16, 24, 32, 40, 48, 56, 64, 72 ] );
+        assert!( x.peers[0].contains( &80 ) );
+        assert_eq!( x.peers[40].len(), 20 + 12 );
+        assert!( x.shaded_cells()[40] && !x.shaded_cells()[1] );
+
+        let hyper = constraints( Variant::Hyper );
+        assert_eq!( hyper.units[ 3 * GRID_SQRT ], vec![ 10, 11, 12, 19, 20, 21, 28, 29, 30 ] );
+        assert_eq!( constraints( Variant::CentreDot ).units[ 3 * GRID_SQRT ], vec![ 10, 13, 16, 37, 40, 43, 64, 67, 70 ] );
+        assert_eq!( constraints( Variant::Asterisk ).units[ 3 * GRID_SQRT ], ASTERISK.to_vec() );
+    }
+
+    #[test]
+    fn chess_variants_add_peers_only() {
+        let knight = constraints( Variant::AntiKnight );
+        assert_eq!( knight.units.len(), 3 * GRID_SQRT );
+        // both knight moves from a corner stay inside its box
+        assert!( knight.peers[0].contains( &11 ) && knight.peers[0].contains( &19 ) );
+        assert_eq!( knight.peers[0].len(), 20 );
+        assert_eq!( knight.peers[40].len(), 20 + 8 );
+        assert!( knight.peers[40].contains( &21 ) && knight.peers[40].contains( &59 ) );
+
+        // only the diagonal king moves can leave the row, column and box
+        let king = constraints( Variant::AntiKing );
+        assert_eq!( king.peers[40].len(), 20 );
+        assert_eq!( king.peers[30].len(), 20 + 3 );
+        assert!( king.peers[30].contains( &20 ) && king.peers[30].contains( &22 ) && king.peers[30].contains( &38 ) );
+    }
+
+    #[test]
+    fn groups_add_peers_but_not_units() {
+        let c = Constraints::new( Variant::Standard, &standard_regions(), &[ vec![ 0, 80 ] ] );
+        assert_eq!( c.units.len(), 3 * GRID_SQRT );
+        assert!( c.peers[0].contains( &80 ) && c.peers[80].contains( &0 ) );
+        assert_eq!( c.peers[0].len(), 21 );
+    }
+    #[test]
+    fn samurai_grids_share_their_corner_blocks() {
+        let board = Board::of( Variant::Samurai );
+        assert_eq!( board.size, 5 * GRID_SIZE - 4 * GRID_SQRT );
+        assert_eq!( board.cell_at( 0, GRID_SQRT ), None );
+        assert_eq!( board.cell_name( GRID_SQRT ), "r1c13" );
+        assert_eq!( board.grids[2].cells[0], board.grids[0].cells[ GRID_SIZE - 1 - 2 * ( GRID_SQRT + 1 ) ] );
+
+        let c = constraints( Variant::Samurai );
+        assert_eq!( c.units.len(), 5 * 3 * GRID_SQRT - 4 );
+        assert!( c.units.iter().all( |unit| unit.len() == GRID_SQRT ) );
+        assert_eq!( unit_name( Variant::Samurai, 3 * GRID_SQRT ), "row 1 of grid 2" );
+        // a cell of a shared block also sees the row and column of the centre grid
+        assert_eq!( c.peers[0].len(), 20 );
+        assert_eq!( c.peers[ board.grids[2].cells[0] ].len(), 20 + 12 );
+    }
+}